@@ -0,0 +1,60 @@
+// throughput of a 10k value pipeline, `cargo bench --features local,sync` compares the flavours
+use std::time::{Duration, Instant};
+
+const VALUES: u64 = 10_000;
+const ROUNDS: u32 = 100;
+
+// the same pipeline against either flavour, which only differ in their paths
+macro_rules! pipeline {
+    ($name:ident, $($flavour:tt)*) => {
+        fn $name() -> Duration {
+            use std::sync::atomic::{AtomicU64, Ordering};
+
+            use $($flavour)*::*;
+
+            let publisher: Shared<Publisher<u64>> = Publisher::new();
+            let subscriber: Shared<Subscriber<u64>> = Subscriber::new();
+            let sum = Shared::new(AtomicU64::new(0));
+            let sum_ref = Shared::clone(&sum);
+            let _sink = subscriber
+                .map(|v| v * 3)
+                .filter(|v| v % 2 == 0)
+                .scan(0, |acc, v| acc + v)
+                .sink(move |v| sum_ref.store(*v, Ordering::Relaxed));
+            let _subscription = publisher.receive_subscriber(&subscriber);
+            let start = Instant::now();
+            for v in 0..VALUES {
+                publisher.send_value(&v);
+            }
+            let elapsed = start.elapsed();
+            assert_eq!(sum.load(Ordering::Relaxed), (0..VALUES).map(|v| v * 3).filter(|v| v % 2 == 0).sum::<u64>());
+            elapsed
+        }
+    };
+}
+
+pipeline!(shared_pipeline, smelter_reflux);
+
+#[cfg(feature = "sync")]
+pipeline!(sync_pipeline, smelter_reflux::sync);
+
+#[cfg(feature = "local")]
+pipeline!(local_pipeline, smelter_reflux::local);
+
+fn measure(mode: &str, pipeline: fn() -> Duration) {
+    // warm up
+    pipeline();
+    let total: Duration = (0..ROUNDS).map(|_| pipeline()).sum();
+    let per_round = total / ROUNDS;
+    let per_value = per_round.as_nanos() as f64 / VALUES as f64;
+    let throughput = VALUES as f64 / per_round.as_secs_f64();
+    println!("{mode}: {per_round:?} per {VALUES} values, {per_value:.1} ns/value, {throughput:.0} values/s");
+}
+
+fn main() {
+    measure("Arc/Mutex", shared_pipeline);
+    #[cfg(feature = "sync")]
+    measure("sync (Arc/Mutex, Send + Sync)", sync_pipeline);
+    #[cfg(feature = "local")]
+    measure("local (Rc/RefCell)", local_pipeline);
+}
@@ -0,0 +1,45 @@
+use super::{Lock, Threadsafe};
+
+pub trait Cancellable {
+    fn cancel(&self);
+}
+
+type Cancel = Box<threadsafe_dyn!(send FnOnce())>;
+
+pub struct AnyCancellable {
+    cancel: Lock<Option<Cancel>>,
+}
+
+impl AnyCancellable {
+    pub fn new<F>(f: F) -> Self where F: FnOnce(), F: Threadsafe, F: 'static {
+        Self {
+            cancel: Lock::new(Some(Box::new(f))),
+        }
+    }
+
+    pub fn from_cancellable<C>(cancellable: C) -> Self where C: Cancellable, C: Threadsafe, C: 'static {
+        Self::new(move || cancellable.cancel())
+    }
+
+    pub fn store_in(self, cancellables: &mut Vec<AnyCancellable>) {
+        cancellables.push(self);
+    }
+}
+
+impl Cancellable for AnyCancellable {
+    fn cancel(&self) {
+        // take the closure out first so that it runs without holding the lock
+        let cancel = self.cancel.lock()
+            .ok()
+            .and_then(|mut guard| guard.take());
+        if let Some(cancel) = cancel {
+            cancel();
+        }
+    }
+}
+
+impl Drop for AnyCancellable {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
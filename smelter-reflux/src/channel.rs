@@ -0,0 +1,247 @@
+use std::{sync::mpsc::{Receiver, Sender, TryRecvError}, time::Duration};
+
+use super::{AnyCancellable, Completion, Lock, Publish, Publisher, Scheduler, Shared, Subscriber, Threadsafe, WeakShared};
+
+// how often an empty channel is checked again
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+impl<T, E> Publisher<T, E> where T: Threadsafe, T: 'static, E: Clone, E: Threadsafe, E: 'static {
+    // sends what arrives on the channel from actions on the scheduler, finishing once every sender is gone.
+    // the channel is polled every `POLL_INTERVAL`, so a value can wait that long and an idle channel
+    // keeps an action pending until the last subscriber is gone. the immediate and queue schedulers
+    // do not wait out the interval, on them this drains the channel before returning
+    pub fn from_receiver<S>(receiver: Receiver<T>, scheduler: &Shared<S>) -> Shared<Self> where S: Scheduler, S: Threadsafe, S: 'static {
+        let publisher = Publisher::new();
+        let pump = Shared::new(Pump {
+            receiver: Lock::new(receiver),
+            publisher: Shared::downgrade(&publisher),
+            scheduler: Shared::clone(scheduler),
+            state: Lock::new(PumpState {
+                scheduled: None,
+                subscribed: false,
+                pumping: false,
+                repeat: false,
+            }),
+        });
+        pump.schedule(Duration::ZERO);
+        publisher
+    }
+}
+
+struct Pump<T, E, S> {
+    receiver: Lock<Receiver<T>>,
+    publisher: WeakShared<Publisher<T, E>>,
+    scheduler: Shared<S>,
+    state: Lock<PumpState>,
+}
+
+struct PumpState {
+    scheduled: Option<AnyCancellable>,
+    // polling ends once the publisher had subscribers and has none anymore
+    subscribed: bool,
+    // a scheduler running the next poll right away has it run by the current one instead of nesting
+    pumping: bool,
+    repeat: bool,
+}
+
+impl<T, E, S> Pump<T, E, S> where T: Threadsafe, T: 'static, E: Clone, E: Threadsafe, E: 'static, S: Scheduler, S: Threadsafe, S: 'static {
+    fn schedule(self: &Shared<Self>, delay: Duration) {
+        let pump = Shared::clone(self);
+        let scheduled = self.scheduler.schedule_after(delay, Box::new(move || pump.pump()));
+        // the previous action is the one running now, if any
+        let previous = self.state.lock()
+            .map(|mut guard| guard.scheduled.replace(scheduled))
+            .unwrap_or_default();
+        drop(previous);
+    }
+
+    fn pump(self: &Shared<Self>) {
+        let nested = self.state.lock()
+            .map(|mut guard| {
+                let nested = std::mem::replace(&mut guard.pumping, true);
+                guard.repeat = nested;
+                nested
+            })
+            .unwrap_or(true);
+        if nested {
+            return;
+        }
+        while self.poll() {
+            self.schedule(POLL_INTERVAL);
+            let repeat = self.state.lock()
+                .map(|mut guard| std::mem::take(&mut guard.repeat))
+                .unwrap_or(false);
+            if !repeat {
+                break;
+            }
+        }
+        if let Ok(mut guard) = self.state.lock() {
+            guard.pumping = false;
+        }
+    }
+
+    // sends what the channel holds, telling whether to poll again
+    fn poll(&self) -> bool {
+        // stops once nobody holds the publisher anymore
+        let Some(publisher) = self.publisher.upgrade() else {
+            return false
+        };
+        let subscribed = publisher.has_subscriptions();
+        let unsubscribed = self.state.lock()
+            .map(|mut guard| std::mem::replace(&mut guard.subscribed, subscribed) && !subscribed)
+            .unwrap_or(true);
+        if unsubscribed {
+            return false;
+        }
+        loop {
+            let received = self.receiver.lock()
+                .map(|guard| guard.try_recv())
+                .unwrap_or(Err(TryRecvError::Disconnected));
+            match received {
+                Ok(v) => publisher.send_value(&v),
+                Err(TryRecvError::Empty) => return true,
+                Err(TryRecvError::Disconnected) => {
+                    publisher.send_completion(&Completion::Finished);
+                    return false
+                },
+            }
+        }
+    }
+}
+
+impl<T, E> Subscriber<T, E> where T: Clone, T: Threadsafe, T: 'static, E: Clone, E: Threadsafe, E: 'static {
+    // forwards the values received to the channel, completion drops the sender and so disconnects it.
+    // a receiver hanging up releases the sinks forwarding to it, other sinks of this subscriber stay.
+    // the sinks are only `Send` in the flavour whose `shared.rs` makes sinks `Send`
+    #[allow(clippy::arc_with_non_send_sync)]
+    pub fn into_sender(self: &Shared<Self>, sender: Sender<T>) -> AnyCancellable {
+        let sender = Shared::new(Lock::new(Some(sender)));
+        let sinks: Shared<Lock<Vec<AnyCancellable>>> = Shared::new(Lock::new(vec![]));
+        let completion_sender = Shared::clone(&sender);
+        let completion = self.sink_completion(move |_| {
+            let sender = completion_sender.lock()
+                .ok()
+                .and_then(|mut guard| guard.take());
+            drop(sender);
+        });
+        let released = Shared::downgrade(&sinks);
+        let value = self.sink(move |v| {
+            let sent = sender.lock()
+                .map(|guard| guard.as_ref().map(|sender| sender.send(v.clone()).is_ok()).unwrap_or(false))
+                .unwrap_or(false);
+            if sent {
+                return;
+            }
+            let released = released.upgrade()
+                .and_then(|sinks| sinks.lock().ok().map(|mut guard| std::mem::take(&mut *guard)))
+                .unwrap_or_default();
+            drop(released);
+        });
+        if let Ok(mut guard) = sinks.lock() {
+            guard.push(value);
+            guard.push(completion);
+        }
+        AnyCancellable::new(move || drop(sinks))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::mpsc, time::Duration};
+
+    use super::super::*;
+    use super::super::testing::*;
+
+    #[test]
+    fn from_receiver() {
+        let scheduler = VirtualTimeScheduler::new();
+        let (sender, receiver) = mpsc::channel();
+        let publisher: Shared<Publisher<u64>> = Publisher::from_receiver(receiver, &scheduler);
+        let recorded = publisher.subscribe()
+            .record(&scheduler);
+        sender.send(1).unwrap();
+        sender.send(2).unwrap();
+        assert!(recorded.values().is_empty());
+        scheduler.advance_by(Duration::ZERO);
+        assert_eq!(recorded.values(), vec![1, 2]);
+        sender.send(3).unwrap();
+        scheduler.advance_by(Duration::from_millis(10));
+        assert_eq!(recorded.values(), vec![1, 2, 3]);
+        assert_eq!(recorded.completion(), None);
+        drop(sender);
+        scheduler.advance_by(Duration::from_millis(10));
+        assert_eq!(recorded.completion(), Some(Completion::Finished));
+        assert!(scheduler.is_idle());
+    }
+
+    #[test]
+    fn from_receiver_dropped() {
+        let scheduler = VirtualTimeScheduler::new();
+        let (_sender, receiver) = mpsc::channel::<u64>();
+        let publisher: Shared<Publisher<u64>> = Publisher::from_receiver(receiver, &scheduler);
+        scheduler.advance_by(Duration::from_millis(10));
+        assert!(!scheduler.is_idle());
+        drop(publisher);
+        scheduler.advance_by(Duration::from_millis(10));
+        assert!(scheduler.is_idle());
+    }
+
+    #[test]
+    fn from_receiver_unsubscribed() {
+        let scheduler = VirtualTimeScheduler::new();
+        let (_sender, receiver) = mpsc::channel::<u64>();
+        let publisher: Shared<Publisher<u64>> = Publisher::from_receiver(receiver, &scheduler);
+        let recorded = publisher.subscribe()
+            .record(&scheduler);
+        scheduler.advance_by(Duration::from_millis(10));
+        assert!(!scheduler.is_idle());
+        drop(recorded);
+        scheduler.advance_by(Duration::from_millis(10));
+        assert!(scheduler.is_idle());
+    }
+
+    #[test]
+    fn from_receiver_immediate() {
+        let scheduler = ImmediateScheduler::new();
+        let (sender, receiver) = mpsc::channel::<u64>();
+        let thread = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            drop(sender);
+        });
+        // polls without nesting until the sender is gone
+        let publisher: Shared<Publisher<u64>> = Publisher::from_receiver(receiver, &scheduler);
+        thread.join().unwrap();
+        assert!(publisher.is_completed());
+    }
+
+    #[test]
+    fn into_sender() {
+        let (sender, receiver) = mpsc::channel();
+        let publisher: Shared<Publisher<u64>> = Publisher::new();
+        let subscriber = publisher.subscribe();
+        let _sender = subscriber.into_sender(sender);
+        publisher.send_value(&1);
+        publisher.send_value(&2);
+        assert_eq!(receiver.try_recv(), Ok(1));
+        assert_eq!(receiver.try_recv(), Ok(2));
+        assert_eq!(receiver.try_recv(), Err(mpsc::TryRecvError::Empty));
+        publisher.send_completion(&Completion::Finished);
+        assert_eq!(receiver.try_recv(), Err(mpsc::TryRecvError::Disconnected));
+    }
+
+    #[test]
+    fn into_sender_disconnected() {
+        let (sender, receiver) = mpsc::channel();
+        let publisher: Shared<Publisher<u64>> = Publisher::new();
+        let subscriber = publisher.subscribe();
+        let recorded = TestSubscriber::new(&subscriber);
+        let _sender = subscriber.into_sender(sender);
+        publisher.send_value(&1);
+        drop(receiver);
+        publisher.send_value(&2);
+        publisher.send_value(&3);
+        // only the sinks forwarding to the channel are gone
+        assert_eq!(recorded.values(), vec![1, 2, 3]);
+        assert!(!subscriber.is_completed());
+    }
+}
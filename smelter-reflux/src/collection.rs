@@ -0,0 +1,561 @@
+use std::{collections::{HashMap, VecDeque}, hash::Hash};
+
+use super::{Lock, Publish, Publisher, ReentrancyPolicy, Shared, Subscriber, Threadsafe};
+
+// indices refer to the values as left by the changes before it in the same change set
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Change<T> {
+    Insert { index: usize, value: T },
+    Remove { index: usize, value: T },
+    Move { from: usize, to: usize },
+    Update { index: usize, value: T },
+    Reset(Vec<T>),
+}
+
+pub type ChangeSet<T> = Vec<Change<T>>;
+
+impl<T> Change<T> where T: Clone {
+    pub fn apply(&self, values: &mut Vec<T>) {
+        match self {
+            Change::Insert { index, value } => values.insert(*index, value.clone()),
+            Change::Remove { index, .. } => {
+                values.remove(*index);
+            },
+            Change::Move { from, to } => {
+                let value = values.remove(*from);
+                values.insert(*to, value);
+            },
+            Change::Update { index, value } => values[*index] = value.clone(),
+            Change::Reset(v) => *values = v.clone(),
+        }
+    }
+}
+
+impl<T> Change<T> where T: Clone, T: Eq, T: Hash {
+    // the changes turning `old` into `new`, values outside of the longest common subsequence
+    // are removed or inserted, and a value both removed and inserted is moved instead.
+    // past `MAX_EDITS` removals and insertions the values are reset to `new` instead
+    pub fn diff(old: &[T], new: &[T]) -> ChangeSet<T> {
+        let prefix = old.iter()
+            .zip(new.iter())
+            .take_while(|(a, b)| a == b)
+            .count();
+        let suffix = old[prefix..].iter().rev()
+            .zip(new[prefix..].iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+        let Some(kept) = common_subsequence(&old[prefix..old.len() - suffix], &new[prefix..new.len() - suffix]) else {
+            return vec![Change::Reset(new.to_vec())]
+        };
+        // the new index each remaining old value ends up at
+        let mut targets: Vec<Option<usize>> = vec![None; old.len()];
+        let mut sources: Vec<Option<usize>> = vec![None; new.len()];
+        let common = (0..prefix)
+            .map(|i| (i, i))
+            .chain(kept.into_iter().map(|(i, j)| (prefix + i, prefix + j)))
+            .chain((1..=suffix).map(|k| (old.len() - k, new.len() - k)));
+        for (i, j) in common {
+            targets[i] = Some(j);
+            sources[j] = Some(i);
+        }
+        let mut removed: HashMap<&T, VecDeque<usize>> = HashMap::new();
+        for (i, v) in old.iter().enumerate() {
+            if targets[i].is_none() {
+                removed.entry(v).or_default().push_back(i);
+            }
+        }
+        let mut moved = vec![false; new.len()];
+        for (j, v) in new.iter().enumerate() {
+            if sources[j].is_some() {
+                continue;
+            }
+            if let Some(i) = removed.get_mut(v).and_then(|v| v.pop_front()) {
+                targets[i] = Some(j);
+                sources[j] = Some(i);
+                moved[j] = true;
+            }
+        }
+        let mut changes = vec![];
+        for (i, v) in old.iter().enumerate().rev() {
+            if targets[i].is_none() {
+                changes.push(Change::Remove { index: i, value: v.clone() });
+            }
+        }
+        // every value is placed right after the previously placed one, values waiting
+        // to be moved may still sit in between; a position counts the old values left
+        // before it and the values placed after each of those old values
+        let mut remaining = Counts::new(old.len());
+        for (i, target) in targets.iter().enumerate() {
+            if target.is_some() {
+                remaining.insert(i);
+            }
+        }
+        // placed values by the old value they follow, shifted by one for those before every old value
+        let mut placed = Counts::new(old.len() + 1);
+        let mut anchor = 0;
+        for (j, v) in new.iter().enumerate() {
+            let cursor = remaining.before(anchor) + placed.before(anchor + 1);
+            match sources[j] {
+                None => {
+                    changes.push(Change::Insert { index: cursor, value: v.clone() });
+                    placed.insert(anchor);
+                },
+                Some(i) if moved[j] => {
+                    let from = remaining.before(i) + placed.before(i + 1);
+                    if from == cursor {
+                        anchor = i + 1;
+                    } else {
+                        remaining.remove(i);
+                        let to = if from < cursor { cursor - 1 } else { cursor };
+                        changes.push(Change::Move { from, to });
+                        placed.insert(anchor);
+                    }
+                },
+                Some(i) => {
+                    anchor = i + 1;
+                },
+            }
+        }
+        changes
+    }
+}
+
+// a count per index, summed over the indices before one in logarithmic time
+struct Counts {
+    tree: Vec<usize>,
+}
+
+impl Counts {
+    fn new(len: usize) -> Self {
+        Self {
+            tree: vec![0; len + 1],
+        }
+    }
+
+    fn insert(&mut self, index: usize) {
+        let mut k = index + 1;
+        while k < self.tree.len() {
+            self.tree[k] += 1;
+            k += k & k.wrapping_neg();
+        }
+    }
+
+    fn remove(&mut self, index: usize) {
+        let mut k = index + 1;
+        while k < self.tree.len() {
+            self.tree[k] -= 1;
+            k += k & k.wrapping_neg();
+        }
+    }
+
+    fn before(&self, index: usize) -> usize {
+        let mut sum = 0;
+        let mut k = index;
+        while k > 0 {
+            sum += self.tree[k];
+            k -= k & k.wrapping_neg();
+        }
+        sum
+    }
+}
+
+// how many values a diff removes and inserts at most before resetting instead
+const MAX_EDITS: isize = 2_000;
+
+// index pairs of a longest common subsequence, in order, found after Myers with its linear space
+// refinement in O((n + m) d) time and O(n + m) space for d values removed or inserted,
+// none once d goes past `MAX_EDITS`
+fn common_subsequence<T>(a: &[T], b: &[T]) -> Option<Vec<(usize, usize)>> where T: Eq {
+    let size = 2 * (a.len() + b.len()) + 3;
+    let mut furthest = (vec![0; size], vec![0; size]);
+    let mut pairs = vec![];
+    bisect(a, b, (0, 0), &mut furthest, &mut pairs)?;
+    Some(pairs)
+}
+
+// splits both sequences at the middle of an edit script between them until only common ends are left
+fn bisect<T>(a: &[T], b: &[T], offset: (usize, usize), furthest: &mut (Vec<isize>, Vec<isize>), pairs: &mut Vec<(usize, usize)>) -> Option<()> where T: Eq {
+    let prefix = a.iter()
+        .zip(b.iter())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let suffix = a[prefix..].iter().rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    pairs.extend((0..prefix).map(|i| (offset.0 + i, offset.1 + i)));
+    let (a_middle, b_middle) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+    if !a_middle.is_empty() && !b_middle.is_empty() {
+        let (x, y) = middle_snake(a_middle, b_middle, furthest)?;
+        let start = (offset.0 + prefix, offset.1 + prefix);
+        bisect(&a_middle[..x], &b_middle[..y], start, furthest, pairs)?;
+        bisect(&a_middle[x..], &b_middle[y..], (start.0 + x, start.1 + y), furthest, pairs)?;
+    }
+    pairs.extend((0..suffix).map(|i| (offset.0 + a.len() - suffix + i, offset.1 + b.len() - suffix + i)));
+    Some(())
+}
+
+// a point both halves of a shortest edit script pass through, searched from both ends at once.
+// the sequences differ at both ends, so the point splits them into smaller ones
+fn middle_snake<T>(a: &[T], b: &[T], furthest: &mut (Vec<isize>, Vec<isize>)) -> Option<(usize, usize)> where T: Eq {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let delta = n - m;
+    let odd = delta % 2 != 0;
+    // the furthest x reached on each diagonal k = x - y, offset by `n + m + 1`.
+    // backwards both are counted from the ends, diagonal k there meets `delta - k` forwards
+    let (forward, backward) = furthest;
+    let at = |k: isize| (k + n + m + 1) as usize;
+    forward[at(1)] = 0;
+    backward[at(1)] = 0;
+    // both searches meet after half of the edits at the latest
+    for d in 0..=(n + m + 1).min(MAX_EDITS + 1) / 2 {
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && forward[at(k - 1)] < forward[at(k + 1)]) {
+                forward[at(k + 1)]
+            } else {
+                forward[at(k - 1)] + 1
+            };
+            let start = (x, x - k);
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            forward[at(k)] = x;
+            if odd && (k - delta).abs() < d && x + backward[at(delta - k)] >= n {
+                return Some((start.0 as usize, start.1 as usize));
+            }
+        }
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && backward[at(k - 1)] < backward[at(k + 1)]) {
+                backward[at(k + 1)]
+            } else {
+                backward[at(k - 1)] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[(n - x - 1) as usize] == b[(m - y - 1) as usize] {
+                x += 1;
+                y += 1;
+            }
+            backward[at(k)] = x;
+            if !odd && (k - delta).abs() <= d && x + forward[at(delta - k)] >= n {
+                return Some(((n - x) as usize, (m - y) as usize));
+            }
+        }
+    }
+    None
+}
+
+// a list publishing what changed on every mutation, changes made inside `batch` are published together
+pub struct ObservableVec<T> where T: Clone, T: Threadsafe, T: 'static {
+    publisher: Shared<Publisher<ChangeSet<T>>>,
+    state: Lock<ObservableVecState<T>>,
+}
+
+struct ObservableVecState<T> {
+    values: Vec<T>,
+    batch_depth: usize,
+    pending: ChangeSet<T>,
+}
+
+impl<T> ObservableVec<T> where T: Clone, T: Threadsafe, T: 'static {
+    pub fn new(values: Vec<T>) -> Shared<Self> {
+        let vec = Self {
+            // a mutation from inside a sink is published after the current change set
+            publisher: Publisher::with_reentrancy(ReentrancyPolicy::Enqueue),
+            state: Lock::new(ObservableVecState {
+                values,
+                batch_depth: 0,
+                pending: vec![],
+            }),
+        };
+        Shared::new(vec)
+    }
+
+    pub fn publisher(&self) -> &Shared<Publisher<ChangeSet<T>>> {
+        &self.publisher
+    }
+
+    // only the changes made after subscribing are received, the values up to then are in `values`
+    pub fn subscribe(&self) -> Shared<Subscriber<ChangeSet<T>>> {
+        self.publisher.subscribe()
+    }
+
+    pub fn values(&self) -> Vec<T> {
+        self.state.lock()
+            .map(|guard| guard.values.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.state.lock()
+            .map(|guard| guard.values.len())
+            .unwrap_or_default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, index: usize) -> Option<T> {
+        self.state.lock()
+            .ok()
+            .and_then(|guard| guard.values.get(index).cloned())
+    }
+
+    pub fn push(&self, value: T) {
+        self.mutate(|values| {
+            values.push(value.clone());
+            Some(Change::Insert { index: values.len() - 1, value })
+        });
+    }
+
+    // false when the index is out of bounds
+    pub fn insert(&self, index: usize, value: T) -> bool {
+        self.mutate(|values| {
+            (index <= values.len()).then(|| {
+                values.insert(index, value.clone());
+                Change::Insert { index, value }
+            })
+        })
+    }
+
+    pub fn remove(&self, index: usize) -> Option<T> {
+        let mut removed = None;
+        self.mutate(|values| {
+            (index < values.len()).then(|| {
+                let value = values.remove(index);
+                removed = Some(value.clone());
+                Change::Remove { index, value }
+            })
+        });
+        removed
+    }
+
+    // replaces the value at the index, handing back the previous one
+    pub fn set(&self, index: usize, value: T) -> Option<T> {
+        let mut previous = None;
+        self.mutate(|values| {
+            let v = values.get_mut(index)?;
+            previous = Some(std::mem::replace(v, value.clone()));
+            Some(Change::Update { index, value })
+        });
+        previous
+    }
+
+    pub fn move_item(&self, from: usize, to: usize) -> bool {
+        self.mutate(|values| {
+            (from < values.len() && to < values.len()).then(|| {
+                let value = values.remove(from);
+                values.insert(to, value);
+                Change::Move { from, to }
+            })
+        })
+    }
+
+    pub fn reset(&self, new: Vec<T>) {
+        self.mutate(|values| {
+            values.clone_from(&new);
+            Some(Change::Reset(new))
+        });
+    }
+
+    pub fn clear(&self) {
+        self.reset(vec![]);
+    }
+
+    // publishes the changes made in `f` as a single change set once the outermost batch ends
+    pub fn batch<F, R>(&self, f: F) -> R where F: FnOnce(&Self) -> R {
+        if let Ok(mut guard) = self.state.lock() {
+            guard.batch_depth += 1;
+        }
+        let result = f(self);
+        let changes = self.state.lock()
+            .ok()
+            .and_then(|mut guard| {
+                guard.batch_depth -= 1;
+                (guard.batch_depth == 0 && !guard.pending.is_empty())
+                    .then(|| std::mem::take(&mut guard.pending))
+            });
+        if let Some(changes) = changes {
+            self.publisher.send_value(&changes);
+        }
+        result
+    }
+
+    // true when `f` made a change
+    fn mutate<F>(&self, f: F) -> bool where F: FnOnce(&mut Vec<T>) -> Option<Change<T>> {
+        let Ok(mut guard) = self.state.lock() else {
+            return false
+        };
+        let Some(change) = f(&mut guard.values) else {
+            return false
+        };
+        if guard.batch_depth > 0 {
+            guard.pending.push(change);
+            return true;
+        }
+        drop(guard);
+        self.publisher.send_value(&vec![change]);
+        true
+    }
+}
+
+impl<T> ObservableVec<T> where T: Clone, T: Eq, T: Hash, T: Threadsafe, T: 'static {
+    // replaces the values, publishing the difference to the previous ones rather than a reset
+    pub fn assign(&self, new: Vec<T>) {
+        let changes = self.state.lock()
+            .ok()
+            .map(|mut guard| {
+                let changes = Change::diff(&guard.values, &new);
+                guard.values = new;
+                if guard.batch_depth > 0 {
+                    guard.pending.extend(changes);
+                    return vec![];
+                }
+                changes
+            })
+            .unwrap_or_default();
+        if !changes.is_empty() {
+            self.publisher.send_value(&changes);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+    use super::super::testing::*;
+
+    fn apply<T>(values: &[T], changes: &[Change<T>]) -> Vec<T> where T: Clone {
+        let mut values = values.to_vec();
+        for change in changes {
+            change.apply(&mut values);
+        }
+        values
+    }
+
+    #[test]
+    fn mutations() {
+        let vec = ObservableVec::new(vec![1, 2, 3]);
+        let subscriber = vec.subscribe();
+        let recorded = TestSubscriber::new(&subscriber);
+        vec.push(4);
+        assert!(vec.insert(0, 0));
+        assert!(!vec.insert(10, 0));
+        assert_eq!(vec.remove(1), Some(1));
+        assert_eq!(vec.set(0, 10), Some(0));
+        assert!(vec.move_item(0, 3));
+        assert_eq!(vec.values(), vec![2, 3, 4, 10]);
+        assert_eq!(recorded.values(), vec![
+            vec![Change::Insert { index: 3, value: 4 }],
+            vec![Change::Insert { index: 0, value: 0 }],
+            vec![Change::Remove { index: 1, value: 1 }],
+            vec![Change::Update { index: 0, value: 10 }],
+            vec![Change::Move { from: 0, to: 3 }],
+        ]);
+        vec.clear();
+        assert!(vec.is_empty());
+        assert_eq!(recorded.values().last(), Some(&vec![Change::Reset(vec![])]));
+    }
+
+    #[test]
+    fn batch() {
+        let vec = ObservableVec::new(vec!["a"]);
+        let subscriber = vec.subscribe();
+        let recorded = TestSubscriber::new(&subscriber);
+        let len = vec.batch(|vec| {
+            vec.push("b");
+            vec.batch(|vec| vec.remove(0));
+            vec.push("c");
+            vec.len()
+        });
+        assert_eq!(len, 2);
+        assert_eq!(recorded.values(), vec![vec![
+            Change::Insert { index: 1, value: "b" },
+            Change::Remove { index: 0, value: "a" },
+            Change::Insert { index: 1, value: "c" },
+        ]]);
+        // an empty batch publishes nothing
+        vec.batch(|_| {});
+        assert_eq!(recorded.values().len(), 1);
+    }
+
+    #[test]
+    fn mirrored() {
+        let vec = ObservableVec::new(vec![1, 2, 3]);
+        let mirror = Shared::new(Lock::new(vec.values()));
+        let r = Shared::clone(&mirror);
+        let subscriber = vec.subscribe();
+        let _sink = subscriber.sink(move |changes: &ChangeSet<i32>| {
+            let mut values = r.lock().unwrap();
+            for change in changes {
+                change.apply(&mut values);
+            }
+        });
+        vec.batch(|vec| {
+            vec.move_item(2, 0);
+            vec.set(1, 5);
+            vec.push(6);
+        });
+        vec.assign(vec![6, 1, 7, 3]);
+        assert_eq!(*mirror.lock().unwrap(), vec.values());
+    }
+
+    #[test]
+    fn diff() {
+        assert!(Change::diff(&[1, 2, 3], &[1, 2, 3]).is_empty());
+        assert_eq!(Change::diff(&[1, 2, 3], &[1, 3]), vec![Change::Remove { index: 1, value: 2 }]);
+        assert_eq!(Change::diff(&[1, 3], &[1, 2, 3]), vec![Change::Insert { index: 1, value: 2 }]);
+        assert_eq!(Change::diff(&[1, 2, 3, 4], &[4, 1, 2, 3]), vec![Change::Move { from: 3, to: 0 }]);
+        assert_eq!(Change::diff(&[1, 2, 3, 4], &[2, 3, 4, 1]), vec![Change::Move { from: 0, to: 3 }]);
+        assert_eq!(Change::diff(&["a", "b"], &["c"]), vec![
+            Change::Remove { index: 1, value: "b" },
+            Change::Remove { index: 0, value: "a" },
+            Change::Insert { index: 0, value: "c" },
+        ]);
+    }
+
+    #[test]
+    fn diff_permutations() {
+        // every pair of sequences over a small alphabet, duplicates included
+        let sequences: Vec<Vec<u8>> = (0..4usize)
+            .flat_map(|len| (0..3usize.pow(len as u32)).map(move |n| {
+                (0..len).map(|k| (n / 3usize.pow(k as u32) % 3) as u8).collect()
+            }))
+            .collect();
+        for old in sequences.iter() {
+            for new in sequences.iter() {
+                let changes = Change::diff(old, new);
+                assert_eq!(apply(old, &changes), *new, "{:?} -> {:?}: {:?}", old, new, changes);
+                assert!(changes.len() <= old.len() + new.len());
+                assert!(!changes.iter().any(|change| matches!(change, Change::Move { from, to } if from == to)));
+            }
+        }
+    }
+
+    #[test]
+    fn diff_disjoint() {
+        // nothing in common, an edit script would be as long as both sequences together
+        let old: Vec<u32> = (0..10_000).collect();
+        let new: Vec<u32> = (10_000..20_000).collect();
+        assert_eq!(Change::diff(&old, &new), vec![Change::Reset(new.clone())]);
+        // within the bound the values are still edited
+        let new: Vec<u32> = (0..9_000).chain(10_000..10_500).collect();
+        let changes = Change::diff(&old, &new);
+        assert_eq!(apply(&old, &changes), new);
+        assert_eq!(changes.len(), 1_500);
+    }
+
+    #[test]
+    fn diff_large() {
+        // few differences between long sequences, a table over both would not fit
+        let old: Vec<u32> = (0..100_000).collect();
+        let mut new = old.clone();
+        new.swap(10, 90_000);
+        new.insert(50_000, 100_000);
+        let changes = Change::diff(&old, &new);
+        assert_eq!(apply(&old, &changes), new);
+        assert_eq!(changes.len(), 3);
+    }
+}
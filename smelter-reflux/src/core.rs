@@ -0,0 +1,1360 @@
+use std::{collections::VecDeque, convert::Infallible, marker::PhantomData, ops::Add};
+
+// the flavour picks the reference counting, locking and `Threadsafe` bounds of everything below
+pub use super::shared::*;
+mod cancellable;
+pub use self::cancellable::*;
+mod scheduler;
+pub use self::scheduler::*;
+mod subject;
+pub use self::subject::*;
+mod deferred;
+pub use self::deferred::*;
+mod property;
+pub use self::property::*;
+mod collection;
+pub use self::collection::*;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+mod channel;
+// tracing is shared by the flavours, it only goes by addresses
+use crate::trace;
+pub use crate::trace::{enable_tracing, trace_graph, TraceEdge, TraceEdgeKind, TraceGraph, TraceNode, TraceNodeKind, Tracing};
+#[cfg(feature = "futures")]
+mod stream;
+#[cfg(feature = "futures")]
+pub use self::stream::*;
+
+mod operator;
+pub use self::operator::{Breakpoints, EventHandlers, Multicast, ThrottleEdge};
+
+pub trait Subscribe {
+    type Input;
+    type Failure;
+
+    fn receive_subscription(&self, subscription: Shared<Subscription<Self::Input, Self::Failure>>);
+    fn receive_value(&self, v: &Self::Input) -> Demand;
+    fn receive_completion(&self, completion: &Completion<Self::Failure>);
+}
+
+pub trait Publish {
+    type Output;
+    type Failure;
+
+    fn receive_subscriber(&self, subscriber: &Shared<Subscriber<Self::Output, Self::Failure>>) -> AnyCancellable;
+    fn send_value(&self, v: &Self::Output);
+    fn send_completion(&self, completion: &Completion<Self::Failure>);
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Completion<E> {
+    Finished,
+    Failure(E),
+}
+
+pub struct Subscriber<T, E = Infallible> {
+    state: Lock<SubscriberState<T, E>>,
+}
+
+impl<T, E> Subscriber<T, E> {
+    pub fn new() -> Shared<Self> {
+        Self::with_demand(Demand::unlimited())
+    }
+
+    pub fn with_demand(demand: Demand) -> Shared<Self> {
+        let subscriber = Self {
+            state: Lock::new(SubscriberState::new(demand)),
+        };
+        let subscriber = Shared::new(subscriber);
+        trace::created::<T>(trace::address(&*subscriber), TraceNodeKind::Subscriber);
+        subscriber
+    }
+
+    // the demand is shared by every subscription of this subscriber rather than granted to each
+    pub fn request(&self, demand: Demand) where E: Clone {
+        let subscriptions = self.state.lock()
+            .map(|mut guard| {
+                guard.demand = guard.demand + demand;
+                guard.subscriptions.clone()
+            })
+            .unwrap_or_default();
+        for subscription in subscriptions.iter() {
+            subscription.drain();
+        }
+    }
+
+    pub fn sink<F>(self: &Shared<Self>, f: F) -> AnyCancellable where F: Fn(&T), F: Threadsafe, F: 'static, T: Threadsafe, T: 'static, E: Threadsafe, E: 'static {
+        self.push_sink(SubscriberSink::Value(Shared::new(f)))
+    }
+
+    pub fn sink_completion<F>(self: &Shared<Self>, f: F) -> AnyCancellable where F: Fn(&Completion<E>), F: Threadsafe, F: 'static, T: Threadsafe, T: 'static, E: Threadsafe, E: 'static {
+        self.push_sink(SubscriberSink::Completion(Shared::new(f)))
+    }
+
+    // runs every time the subscriber is attached to a publisher, before any value arrives
+    pub(crate) fn sink_subscription<F>(self: &Shared<Self>, f: F) -> AnyCancellable where F: Fn(), F: Threadsafe, F: 'static, T: Threadsafe, T: 'static, E: Threadsafe, E: 'static {
+        self.push_sink(SubscriberSink::Subscription(Shared::new(f)))
+    }
+
+    pub fn map<F, S>(self: &Shared<Self>, f: F) -> Shared<Subscriber<S, E>> where F: Fn(&T) -> S, F: Threadsafe, F: 'static, T: Threadsafe, T: 'static, S: Threadsafe, S: 'static, E: Clone, E: Threadsafe, E: 'static {
+        let (publisher, subscriber) = operator::relay();
+        subscriber.store(self.forward_completion(&publisher));
+        subscriber.store(self.sink(move |v| publisher.send_value(&f(v))));
+        subscriber
+    }
+
+    pub fn bind(self: &Shared<Self>, publisher: &Shared<Publisher<T, E>>) -> AnyCancellable where T: Threadsafe, T: 'static, E: Clone, E: Threadsafe, E: 'static {
+        let publisher = Shared::clone(publisher);
+        self.sink(move |v| publisher.send_value(v))
+    }
+
+    pub fn is_completed(&self) -> bool {
+        self.state.lock()
+            .map(|guard| guard.completion.is_some())
+            .unwrap_or(true)
+    }
+
+    pub(crate) fn completion(&self) -> Option<Completion<E>> where E: Clone {
+        self.state.lock()
+            .ok()
+            .and_then(|guard| guard.completion.clone())
+    }
+
+    // keeps the upstream of a derived subscriber alive as long as the subscriber itself
+    pub(crate) fn store(&self, cancellable: AnyCancellable) {
+        if let Ok(mut guard) = self.state.lock() {
+            guard.cancellables.push(cancellable);
+        }
+    }
+
+    pub(crate) fn forward_completion<S>(self: &Shared<Self>, publisher: &Shared<Publisher<S, E>>) -> AnyCancellable where T: Threadsafe, T: 'static, S: Threadsafe, S: 'static, E: Clone, E: Threadsafe, E: 'static {
+        let publisher = Shared::clone(publisher);
+        self.sink_completion(move |completion| publisher.send_completion(completion))
+    }
+
+    fn push_sink(self: &Shared<Self>, sink: SubscriberSink<T, E>) -> AnyCancellable where T: Threadsafe, T: 'static, E: Threadsafe, E: 'static {
+        let id = self.state.lock()
+            .ok()
+            .map(|mut guard| guard.push_sink(sink));
+        // the sink keeps its subscriber alive, publishers only hold it weakly
+        let subscriber = Shared::clone(self);
+        AnyCancellable::new(move || {
+            if let Some(id) = id {
+                subscriber.remove_sink(id);
+            }
+        })
+    }
+
+    // takes one value out of the outstanding demand, false when there is none left
+    fn consume_demand(&self) -> bool {
+        self.state.lock()
+            .ok()
+            .and_then(|mut guard| {
+                guard.demand = guard.demand.consumed(1)?;
+                Some(())
+            })
+            .is_some()
+    }
+
+    fn remove_subscription(&self, subscription: &Subscription<T, E>) {
+        let removed = self.state.lock()
+            .map(|mut guard| guard.remove_subscription(subscription))
+            .unwrap_or_default();
+        drop(removed);
+    }
+
+    fn remove_sink(&self, id: u64) {
+        let sink = self.state.lock()
+            .ok()
+            .and_then(|mut guard| guard.remove_sink(id));
+        // the sink may own the rest of a chain, so release it outside the lock
+        drop(sink);
+    }
+}
+
+impl<T, E> Drop for Subscriber<T, E> {
+    fn drop(&mut self) {
+        self.cancel();
+        trace::dropped(trace::address(self));
+    }
+}
+
+impl<T, E> Cancellable for Subscriber<T, E> {
+    // detaches from the publishers and releases the upstream sinks of a derived subscriber
+    fn cancel(&self) {
+        let (subscriptions, cancellables) = self.state.lock()
+            .map(|mut guard| (std::mem::take(&mut guard.subscriptions), std::mem::take(&mut guard.cancellables)))
+            .unwrap_or_default();
+        for subscription in subscriptions.iter() {
+            subscription.cancel();
+        }
+        drop(cancellables);
+    }
+}
+
+impl<T, E> Subscribe for Subscriber<T, E> where E: Clone {
+    type Input = T;
+    type Failure = E;
+
+    fn receive_subscription(&self, subscription: Shared<Subscription<Self::Input, Self::Failure>>) {
+        let sinks = self.state.lock()
+            .map(|mut guard| guard.receive_subscription(&subscription))
+            .unwrap_or_default();
+        for sink in sinks.iter() {
+            sink();
+        }
+    }
+
+    fn receive_value(&self, v: &Self::Input) -> Demand {
+        // sinks run on a snapshot without the lock, so that they are free to
+        // send, subscribe or cancel on this subscriber
+        let sinks = self.state.lock()
+            .map(|guard| guard.value_sinks())
+            .unwrap_or_default();
+        trace::delivering(trace::address(self), || {
+            for sink in sinks.iter() {
+                sink(v);
+            }
+        });
+        Demand::nothing()
+    }
+
+    fn receive_completion(&self, completion: &Completion<Self::Failure>) {
+        // completion is delivered once, so the sinks are taken out and run without the lock
+        let state = self.state.lock()
+            .ok()
+            .and_then(|mut guard| guard.receive_completion(completion));
+        if let Some((subscriptions, sinks)) = state {
+            for (_, sink) in sinks.iter() {
+                if let SubscriberSink::Completion(sink) = sink {
+                    sink(completion);
+                }
+            }
+            // a completed subscriber detaches from the rest of its publishers
+            for subscription in subscriptions.iter() {
+                subscription.cancel();
+            }
+        }
+    }
+}
+
+type Sink<T> = Shared<threadsafe_dyn!(Fn(&T))>;
+
+type SubscriptionSink = Shared<threadsafe_dyn!(Fn())>;
+
+enum SubscriberSink<T, E> {
+    Subscription(SubscriptionSink),
+    Value(Sink<T>),
+    Completion(Sink<Completion<E>>),
+}
+
+type SubscriberSinks<T, E> = Vec<(u64, SubscriberSink<T, E>)>;
+// held strongly so that a completion waiting for buffered values outlives the publisher delivering it
+type SubscriberSubscriptions<T, E> = Vec<Shared<Subscription<T, E>>>;
+
+struct SubscriberState<T, E> {
+    v: PhantomData<T>,
+    demand: Demand,
+    // kept for what attaches to the subscriber once it has completed
+    completion: Option<Completion<E>>,
+    sinks: SubscriberSinks<T, E>,
+    next_sink_id: u64,
+    subscriptions: SubscriberSubscriptions<T, E>,
+    cancellables: Vec<AnyCancellable>,
+}
+
+impl<T, E> SubscriberState<T, E> {
+    fn new(demand: Demand) -> Self {
+        Self {
+            v: PhantomData,
+            demand,
+            completion: None,
+            sinks: vec![],
+            next_sink_id: 0,
+            subscriptions: vec![],
+            cancellables: vec![],
+        }
+    }
+
+    fn receive_subscription(&mut self, subscription: &Shared<Subscription<T, E>>) -> Vec<SubscriptionSink> {
+        self.subscriptions.push(Shared::clone(subscription));
+        self.sinks.iter()
+            .filter_map(|(_, sink)| match sink {
+                SubscriberSink::Subscription(sink) => Some(Shared::clone(sink)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn remove_subscription(&mut self, subscription: &Subscription<T, E>) -> SubscriberSubscriptions<T, E> {
+        let (removed, subscriptions) = std::mem::take(&mut self.subscriptions)
+            .into_iter()
+            .partition(|v| std::ptr::eq(v.as_ref(), subscription));
+        self.subscriptions = subscriptions;
+        removed
+    }
+
+    fn value_sinks(&self) -> Vec<Sink<T>> {
+        if self.completion.is_some() {
+            return vec![];
+        }
+        self.sinks.iter()
+            .filter_map(|(_, sink)| match sink {
+                SubscriberSink::Value(sink) => Some(Shared::clone(sink)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn receive_completion(&mut self, completion: &Completion<E>) -> Option<(SubscriberSubscriptions<T, E>, SubscriberSinks<T, E>)> where E: Clone {
+        if self.completion.is_some() {
+            return None;
+        }
+        self.completion = Some(completion.clone());
+        let subscriptions = std::mem::take(&mut self.subscriptions);
+        let sinks = std::mem::take(&mut self.sinks);
+        Some((subscriptions, sinks))
+    }
+
+    fn push_sink(&mut self, sink: SubscriberSink<T, E>) -> u64 {
+        let id = self.next_sink_id;
+        self.next_sink_id += 1;
+        // nothing is delivered after completion
+        if self.completion.is_none() {
+            self.sinks.push((id, sink));
+        }
+        id
+    }
+
+    fn remove_sink(&mut self, id: u64) -> Option<SubscriberSink<T, E>> {
+        let index = self.sinks.iter()
+            .position(|(v, _)| *v == id)?;
+        let (_, sink) = self.sinks.remove(index);
+        Some(sink)
+    }
+}
+
+pub struct Publisher<T, E = Infallible> {
+    state: Lock<PublisherState<T, E>>,
+}
+
+impl<T, E> Publisher<T, E> {
+    pub fn new() -> Shared<Self> {
+        let publisher = Self {
+            state: Lock::new(PublisherState::new()),
+        };
+        let publisher = Shared::new(publisher);
+        // pass weak reference
+        if let Ok(mut guard) = publisher.state.lock() {
+            guard.initialize(&publisher);
+        }
+        trace::created::<T>(trace::address(&*publisher), TraceNodeKind::Publisher);
+        publisher
+    }
+
+    pub fn with_policy(policy: BufferingPolicy) -> Shared<Self> where T: Clone {
+        let publisher = Self::new();
+        if let Ok(mut guard) = publisher.state.lock() {
+            guard.buffering = Some(Buffering { policy, clone: T::clone });
+        }
+        publisher
+    }
+
+    // every new subscriber first receives up to `count` of the latest values
+    pub fn with_replay(count: usize) -> Shared<Self> where T: Clone {
+        let publisher = Self::new();
+        if let Ok(mut guard) = publisher.state.lock() {
+            guard.replay = Some(Replay::new(count, T::clone));
+        }
+        publisher
+    }
+
+    // replays the latest value, starting with `value`, and enqueues reentrant sends
+    pub(crate) fn with_current_value(value: &T) -> Shared<Self> where T: Clone, E: Clone {
+        let publisher = Self::with_reentrancy(ReentrancyPolicy::Enqueue);
+        if let Ok(mut guard) = publisher.state.lock() {
+            let mut replay = Replay::new(1, T::clone);
+            replay.push(value);
+            guard.replay = Some(replay);
+        }
+        publisher
+    }
+
+    pub fn with_reentrancy(policy: ReentrancyPolicy) -> Shared<Self> where T: Clone, E: Clone {
+        let publisher = Self::new();
+        if let Ok(mut guard) = publisher.state.lock() {
+            guard.enqueue = match policy {
+                ReentrancyPolicy::Nested => None,
+                ReentrancyPolicy::Enqueue => Some(Enqueue { value: T::clone, completion: Completion::clone }),
+            };
+        }
+        publisher
+    }
+
+    pub fn is_completed(&self) -> bool {
+        self.state.lock()
+            .map(|guard| guard.completion.is_some())
+            .unwrap_or(true)
+    }
+
+    pub(crate) fn has_subscriptions(&self) -> bool {
+        self.state.lock()
+            .map(|guard| !guard.subscriptions.is_empty())
+            .unwrap_or(false)
+    }
+
+    // a subscriber fed by this publisher, for use with operators taking subscribers
+    pub fn subscribe(self: &Shared<Self>) -> Shared<Subscriber<T, E>> where T: Threadsafe, T: 'static, E: Clone, E: Threadsafe, E: 'static {
+        let subscriber = Subscriber::new();
+        subscriber.store(self.receive_subscriber(&subscriber));
+        subscriber
+    }
+}
+
+impl<T, E> Publish for Publisher<T, E> where T: Threadsafe, T: 'static, E: Clone, E: Threadsafe, E: 'static {
+    type Output = T;
+    type Failure = E;
+
+    fn receive_subscriber(&self, subscriber: &Shared<Subscriber<T, E>>) -> AnyCancellable {
+        let subscription = self.state.lock()
+            .ok()
+            .and_then(|mut guard| guard.receive_subscriber(subscriber));
+        let Some((subscription, replayed, completion)) = subscription else {
+            return AnyCancellable::new(|| {});
+        };
+        trace::subscribed(trace::address(&*subscription), trace::address(self), trace::address(&**subscriber));
+        subscriber.receive_subscription(Shared::clone(&subscription));
+        for v in replayed.iter() {
+            subscription.receive_value(v);
+        }
+        // a late subscriber of a completed publisher receives the completion right away
+        if let Some(completion) = completion {
+            subscription.receive_completion(&completion);
+        }
+        let subscription = Shared::downgrade(&subscription);
+        AnyCancellable::new(move || {
+            if let Some(subscription) = subscription.upgrade() {
+                subscription.cancel();
+            }
+        })
+    }
+
+    fn send_value(&self, v: &Self::Output) {
+        // delivery runs on a snapshot of the subscriptions without holding the lock
+        let subscriptions = self.state.lock()
+            .ok()
+            .and_then(|mut guard| guard.send_value(v));
+        if let Some(subscriptions) = subscriptions {
+            trace::sent(trace::address(self));
+            for subscription in subscriptions.iter() {
+                subscription.receive_value(v);
+            }
+            self.drain();
+        }
+    }
+
+    fn send_completion(&self, completion: &Completion<Self::Failure>) {
+        let subscriptions = self.state.lock()
+            .ok()
+            .and_then(|mut guard| guard.send_completion(completion));
+        if let Some(subscriptions) = subscriptions {
+            for subscription in subscriptions.iter() {
+                subscription.receive_completion(completion);
+            }
+            self.drain();
+        }
+    }
+}
+
+impl<T, E> Drop for Publisher<T, E> {
+    fn drop(&mut self) {
+        trace::dropped(trace::address(self));
+    }
+}
+
+impl<T, E> Publisher<T, E> {
+    fn remove_subscription(&self, subscription: &Subscription<T, E>) {
+        let removed = self.state.lock()
+            .map(|mut guard| guard.remove_subscription(subscription))
+            .unwrap_or_default();
+        drop(removed);
+    }
+
+    // delivers what was sent from inside sinks while the outermost delivery was running
+    fn drain(&self) where E: Clone {
+        loop {
+            let next = self.state.lock()
+                .ok()
+                .and_then(|mut guard| guard.next_enqueued());
+            match next {
+                Some((subscriptions, Enqueued::Value(v))) => {
+                    for subscription in subscriptions.iter() {
+                        subscription.receive_value(&v);
+                    }
+                },
+                Some((subscriptions, Enqueued::Completion(completion))) => {
+                    for subscription in subscriptions.iter() {
+                        subscription.receive_completion(&completion);
+                    }
+                },
+                None => break,
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReentrancyPolicy {
+    // a value sent from inside a sink is delivered right away, before the
+    // outer delivery moves on to the remaining subscribers
+    Nested,
+    // a value sent from inside a sink waits until the outer delivery is over,
+    // so that every subscriber observes values in the order they were sent
+    Enqueue,
+}
+
+struct Enqueue<T, E> {
+    value: fn(&T) -> T,
+    completion: fn(&Completion<E>) -> Completion<E>,
+}
+
+impl<T, E> Clone for Enqueue<T, E> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T, E> Copy for Enqueue<T, E> {}
+
+enum Enqueued<T, E> {
+    Value(T),
+    Completion(Completion<E>),
+}
+
+type PublisherSubscriptions<T, E> = Vec<Shared<Subscription<T, E>>>;
+
+// a new subscription along with the values replayed to it, and the completion when it comes late
+type Attached<T, E> = (Shared<Subscription<T, E>>, Vec<T>, Option<Completion<E>>);
+
+struct PublisherState<T, E> {
+    v: PhantomData<T>,
+    publisher: Option<WeakShared<Publisher<T, E>>>,
+    subscriptions: PublisherSubscriptions<T, E>,
+    buffering: Option<Buffering<T>>,
+    completion: Option<Completion<E>>,
+    enqueue: Option<Enqueue<T, E>>,
+    delivering: bool,
+    queue: VecDeque<Enqueued<T, E>>,
+    replay: Option<Replay<T>>,
+}
+
+impl<T, E> PublisherState<T, E> {
+    fn new() -> Self {
+        Self {
+            v: PhantomData,
+            publisher: None,
+            subscriptions: vec![],
+            buffering: None,
+            completion: None,
+            enqueue: None,
+            delivering: false,
+            queue: VecDeque::new(),
+            replay: None,
+        }
+    }
+
+    fn initialize(&mut self, publisher: &Shared<Publisher<T, E>>) {
+        self.publisher = Some(Shared::downgrade(publisher));
+    }
+
+    fn receive_subscriber(&mut self, subscriber: &Shared<Subscriber<T, E>>) -> Option<Attached<T, E>> where E: Clone {
+        let publisher = self.publisher.clone()
+            .unwrap_or_default();
+        let subscription = Subscription::with_buffering(&publisher, subscriber, self.buffering);
+        // a completed publisher has nothing left to deliver but the completion
+        if self.completion.is_none() {
+            self.subscriptions.push(Shared::clone(&subscription));
+        }
+        let replayed = self.replay.as_ref()
+            .map(|replay| replay.values())
+            .unwrap_or_default();
+        Some((subscription, replayed, self.completion.clone()))
+    }
+
+    fn remove_subscription(&mut self, subscription: &Subscription<T, E>) -> PublisherSubscriptions<T, E> {
+        let (removed, subscriptions) = std::mem::take(&mut self.subscriptions)
+            .into_iter()
+            .partition(|v| std::ptr::eq(v.as_ref(), subscription));
+        self.subscriptions = subscriptions;
+        removed
+    }
+
+    // the subscriptions to deliver to right away, none when the value is enqueued instead
+    fn send_value(&mut self, v: &T) -> Option<PublisherSubscriptions<T, E>> {
+        if self.completion.is_some() {
+            return None;
+        }
+        if let Some(enqueue) = self.enqueue {
+            if self.delivering {
+                self.queue.push_back(Enqueued::Value((enqueue.value)(v)));
+                return None;
+            }
+            self.delivering = true;
+        }
+        if let Some(replay) = self.replay.as_mut() {
+            replay.push(v);
+        }
+        Some(self.subscriptions.clone())
+    }
+
+    fn send_completion(&mut self, completion: &Completion<E>) -> Option<PublisherSubscriptions<T, E>> where E: Clone {
+        if self.completion.is_some() {
+            return None;
+        }
+        self.completion = Some(completion.clone());
+        if let Some(enqueue) = self.enqueue {
+            if self.delivering {
+                self.queue.push_back(Enqueued::Completion((enqueue.completion)(completion)));
+                return None;
+            }
+            self.delivering = true;
+        }
+        Some(std::mem::take(&mut self.subscriptions))
+    }
+
+    fn next_enqueued(&mut self) -> Option<(PublisherSubscriptions<T, E>, Enqueued<T, E>)> {
+        let Some(next) = self.queue.pop_front() else {
+            self.delivering = false;
+            return None
+        };
+        let subscriptions = match &next {
+            Enqueued::Value(v) => {
+                if let Some(replay) = self.replay.as_mut() {
+                    replay.push(v);
+                }
+                self.subscriptions.clone()
+            },
+            Enqueued::Completion(_) => std::mem::take(&mut self.subscriptions),
+        };
+        Some((subscriptions, next))
+    }
+}
+
+struct Replay<T> {
+    count: usize,
+    clone: fn(&T) -> T,
+    values: VecDeque<T>,
+}
+
+impl<T> Replay<T> {
+    fn new(count: usize, clone: fn(&T) -> T) -> Self {
+        Self {
+            count,
+            clone,
+            values: VecDeque::new(),
+        }
+    }
+
+    fn push(&mut self, v: &T) {
+        if self.count == 0 {
+            return;
+        }
+        if self.values.len() >= self.count {
+            self.values.pop_front();
+        }
+        self.values.push_back((self.clone)(v));
+    }
+
+    fn values(&self) -> Vec<T> {
+        self.values.iter()
+            .map(self.clone)
+            .collect()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BufferingPolicy {
+    // values sent while there is no demand are discarded
+    Drop,
+    // keeps up to the given number of values, discarding new ones once full
+    DropNewest(usize),
+    // keeps up to the given number of values, discarding old ones once full
+    DropOldest(usize),
+}
+
+struct Buffering<T> {
+    policy: BufferingPolicy,
+    clone: fn(&T) -> T,
+}
+
+impl<T> Clone for Buffering<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Buffering<T> {}
+
+impl<T> Buffering<T> {
+    fn push(&self, buffer: &mut VecDeque<T>, v: &T) {
+        match self.policy {
+            BufferingPolicy::Drop => {},
+            BufferingPolicy::DropNewest(size) => {
+                if buffer.len() < size {
+                    buffer.push_back((self.clone)(v));
+                }
+            },
+            BufferingPolicy::DropOldest(size) => {
+                if size == 0 {
+                    return;
+                }
+                if buffer.len() >= size {
+                    buffer.pop_front();
+                }
+                buffer.push_back((self.clone)(v));
+            },
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Demand {
+    count: u64,
+}
+
+impl Demand {
+    pub fn nothing() -> Self {
+        Self { count: 0 }
+    }
+
+    pub fn unlimited() -> Self {
+        Self { count: u64::MAX }
+    }
+
+    pub fn max(count: u64) -> Self {
+        Self { count }
+    }
+
+    pub fn is_nothing(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn is_unlimited(&self) -> bool {
+        self.count == u64::MAX
+    }
+
+    pub fn consumed(self, count: u64) -> Option<Self> {
+        if self.count == u64::MAX {
+            Some(self)
+        } else if self.count >= count {
+            Some(Self { count: self.count - count })
+        } else {
+            None
+        }
+    }
+}
+
+impl Add for Demand {
+    type Output = Demand;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self { count: self.count.saturating_add(rhs.count) }
+    }
+}
+
+pub struct Subscription<T, E = Infallible> {
+    state: Lock<SubscriptionState<T, E>>,
+}
+
+impl<T, E> Subscription<T, E> {
+    pub fn new(publisher: &WeakShared<Publisher<T, E>>, subscriber: &Shared<Subscriber<T, E>>) -> Shared<Self> {
+        Self::with_buffering(publisher, subscriber, None)
+    }
+
+    fn with_buffering(publisher: &WeakShared<Publisher<T, E>>, subscriber: &Shared<Subscriber<T, E>>, buffering: Option<Buffering<T>>) -> Shared<Self> {
+        let subscription = Self {
+            state: Lock::new(SubscriptionState::new(publisher, subscriber, buffering)),
+        };
+        Shared::new(subscription)
+    }
+
+    pub fn receive_value(&self, v: &T) where E: Clone {
+        // deliver without holding the lock so that a sink is able to cancel
+        let subscriber = self.state.lock()
+            .ok()
+            .and_then(|mut guard| guard.receive_value(v));
+        if let Some(subscriber) = subscriber {
+            let demand = subscriber.receive_value(v);
+            if !demand.is_nothing() {
+                self.request(demand);
+            }
+        }
+    }
+
+    // the completion waits for the values still buffered, the subscription ends once it is delivered
+    pub fn receive_completion(&self, completion: &Completion<E>) where E: Clone {
+        let subscriber = self.state.lock()
+            .ok()
+            .and_then(|mut guard| guard.receive_completion(completion));
+        if let Some(subscriber) = subscriber {
+            subscriber.receive_completion(completion);
+        }
+    }
+
+    // adds to the demand of the subscriber, which its other subscriptions draw from as well
+    pub fn request(&self, demand: Demand) where E: Clone {
+        let subscriber = self.state.lock()
+            .ok()
+            .and_then(|guard| guard.subscriber.as_ref()?.upgrade());
+        if let Some(subscriber) = subscriber {
+            subscriber.request(demand);
+        }
+    }
+
+    // delivers buffered values while the subscriber has demand left
+    fn drain(&self) where E: Clone {
+        loop {
+            let next = self.state.lock()
+                .ok()
+                .and_then(|mut guard| guard.next_buffered());
+            let Some((subscriber, v)) = next else {
+                break
+            };
+            let demand = subscriber.receive_value(&v);
+            if !demand.is_nothing() {
+                subscriber.request(demand);
+            }
+        }
+        let completion = self.state.lock()
+            .ok()
+            .and_then(|mut guard| guard.buffered_completion());
+        if let Some((subscriber, completion)) = completion {
+            subscriber.receive_completion(&completion);
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.state.lock()
+            .map(|guard| guard.subscriber.as_ref().map(|v| v.strong_count() == 0).unwrap_or(true))
+            .unwrap_or(true)
+    }
+}
+
+impl<T, E> Drop for Subscription<T, E> {
+    fn drop(&mut self) {
+        trace::unsubscribed(trace::address(self));
+    }
+}
+
+impl<T, E> Cancellable for Subscription<T, E> {
+    fn cancel(&self) {
+        let state = self.state.lock()
+            .ok()
+            .map(|mut guard| (guard.publisher.upgrade(), guard.subscriber.take()));
+        if let Some((publisher, subscriber)) = state {
+            if let Some(publisher) = publisher {
+                publisher.remove_subscription(self);
+            }
+            if let Some(subscriber) = subscriber.and_then(|v| v.upgrade()) {
+                subscriber.remove_subscription(self);
+            }
+        }
+    }
+}
+
+type HeldCompletion<T, E> = (Shared<Subscriber<T, E>>, Completion<E>);
+
+struct SubscriptionState<T, E> {
+    publisher: WeakShared<Publisher<T, E>>,
+    // the subscriber is kept alive by its sinks, not by the publisher
+    subscriber: Option<WeakShared<Subscriber<T, E>>>,
+    buffering: Option<Buffering<T>>,
+    buffer: VecDeque<T>,
+    // a completion that arrived while values were still buffered
+    completion: Option<Completion<E>>,
+}
+
+impl<T, E> SubscriptionState<T, E> {
+    fn new(publisher: &WeakShared<Publisher<T, E>>, subscriber: &Shared<Subscriber<T, E>>, buffering: Option<Buffering<T>>) -> Self {
+        Self {
+            publisher: WeakShared::clone(publisher),
+            subscriber: Some(Shared::downgrade(subscriber)),
+            buffering,
+            buffer: VecDeque::new(),
+            completion: None,
+        }
+    }
+
+    fn receive_value(&mut self, v: &T) -> Option<Shared<Subscriber<T, E>>> {
+        let subscriber = self.subscriber.as_ref()?.upgrade()?;
+        // values buffered earlier go first
+        if self.buffer.is_empty() && subscriber.consume_demand() {
+            Some(subscriber)
+        } else {
+            if let Some(buffering) = self.buffering.as_ref() {
+                buffering.push(&mut self.buffer, v);
+            }
+            None
+        }
+    }
+
+    fn receive_completion(&mut self, completion: &Completion<E>) -> Option<Shared<Subscriber<T, E>>> where E: Clone {
+        if !self.buffer.is_empty() {
+            self.completion = Some(completion.clone());
+            return None;
+        }
+        self.subscriber.take()?.upgrade()
+    }
+
+    // the held back completion once the last buffered value is delivered
+    fn buffered_completion(&mut self) -> Option<HeldCompletion<T, E>> {
+        if !self.buffer.is_empty() {
+            return None;
+        }
+        let completion = self.completion.take()?;
+        let subscriber = self.subscriber.take()?.upgrade()?;
+        Some((subscriber, completion))
+    }
+
+    fn next_buffered(&mut self) -> Option<(Shared<Subscriber<T, E>>, T)> {
+        let subscriber = self.subscriber.as_ref()?.upgrade()?;
+        if self.buffer.is_empty() || !subscriber.consume_demand() {
+            return None;
+        }
+        let v = self.buffer.pop_front()?;
+        Some((subscriber, v))
+    }
+}
+
+pub struct Property<T> where T: Clone, T: Threadsafe, T: 'static {
+    subject: Shared<CurrentValueSubject<T>>,
+    subscriber: Shared<Subscriber<T>>,
+}
+
+impl<T> Property<T> where T: Clone, T: Threadsafe, T: 'static {
+    pub fn new(value: T) -> Shared<Self> {
+        // accepting from inside a sink of the property waits for the current value to reach every sink
+        let subject = CurrentValueSubject::new(value);
+        let subscriber = subject.publisher().subscribe();
+        let property = Self {
+            subject,
+            subscriber,
+        };
+        Shared::new(property)
+    }
+
+    pub fn publisher(&self) -> &Shared<Publisher<T>> {
+        self.subject.publisher()
+    }
+
+    pub fn subscriber(&self) -> &Shared<Subscriber<T>> {
+        &self.subscriber
+    }
+
+    pub fn accept(&self, v: &T) {
+        self.subject.send(v)
+    }
+
+    pub fn value(&self) -> T {
+        self.subject.value()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_works() {
+        let publisher: Shared<Publisher<u64>> = Publisher::new();
+        let subscriber: Shared<Subscriber<u64>> = Subscriber::new();
+        let x: Shared<Lock<u64>> = Shared::new(Lock::new(0));
+        let r = Shared::clone(&x);
+        let _sink = subscriber
+            .sink(move |v| *r.lock().unwrap() = *v);
+        let _subscription = publisher.receive_subscriber(&subscriber);
+        publisher.send_value(&100);
+        assert_eq!(*x.lock().unwrap(), 100);
+    }
+
+    #[test]
+    fn cancel_subscription() {
+        let publisher: Shared<Publisher<u64>> = Publisher::new();
+        let subscriber: Shared<Subscriber<u64>> = Subscriber::new();
+        let x: Shared<Lock<u64>> = Shared::new(Lock::new(0));
+        let r = Shared::clone(&x);
+        let _sink = subscriber
+            .sink(move |v| *r.lock().unwrap() += *v);
+        let subscription = publisher.receive_subscriber(&subscriber);
+        publisher.send_value(&1);
+        subscription.cancel();
+        publisher.send_value(&10);
+        assert_eq!(*x.lock().unwrap(), 1);
+        assert!(publisher.state.lock().unwrap().subscriptions.is_empty());
+    }
+
+    #[test]
+    fn drop_cancellables() {
+        let publisher: Shared<Publisher<u64>> = Publisher::new();
+        let subscriber: Shared<Subscriber<u64>> = Subscriber::new();
+        let x: Shared<Lock<u64>> = Shared::new(Lock::new(0));
+        let r = Shared::clone(&x);
+        let mut cancellables = vec![];
+        subscriber
+            .map(|v| v * 2)
+            .sink(move |v| *r.lock().unwrap() += *v)
+            .store_in(&mut cancellables);
+        publisher.receive_subscriber(&subscriber)
+            .store_in(&mut cancellables);
+        publisher.send_value(&1);
+        drop(cancellables);
+        publisher.send_value(&10);
+        assert_eq!(*x.lock().unwrap(), 2);
+        assert!(publisher.state.lock().unwrap().subscriptions.is_empty());
+    }
+
+    #[test]
+    fn drop_derived() {
+        let publisher: Shared<Publisher<u64>> = Publisher::new();
+        let subscriber: Shared<Subscriber<u64>> = Subscriber::new();
+        let x: Shared<Lock<u64>> = Shared::new(Lock::new(0));
+        let _subscription = publisher.receive_subscriber(&subscriber);
+        for _ in 0..100 {
+            let r = Shared::clone(&x);
+            let sink = subscriber
+                .map(|v| v * 2)
+                .sink(move |v| *r.lock().unwrap() += *v);
+            drop(sink);
+        }
+        publisher.send_value(&1);
+        assert_eq!(*x.lock().unwrap(), 0);
+        assert!(subscriber.state.lock().unwrap().sinks.is_empty());
+        // cancelling releases the upstream sinks even while the derived subscriber is held
+        let r = Shared::clone(&x);
+        let mapped = subscriber.map(|v| v * 2);
+        let _sink = mapped.sink(move |v| *r.lock().unwrap() += *v);
+        publisher.send_value(&1);
+        mapped.cancel();
+        publisher.send_value(&10);
+        assert_eq!(*x.lock().unwrap(), 2);
+        assert!(subscriber.state.lock().unwrap().sinks.is_empty());
+    }
+
+    #[test]
+    fn limited_demand() {
+        let publisher: Shared<Publisher<u64>> = Publisher::new();
+        let subscriber: Shared<Subscriber<u64>> = Subscriber::with_demand(Demand::max(3));
+        let x: Shared<Lock<Vec<u64>>> = Shared::new(Lock::new(vec![]));
+        let r = Shared::clone(&x);
+        let _sink = subscriber
+            .sink(move |v| r.lock().unwrap().push(*v));
+        let _subscription = publisher.receive_subscriber(&subscriber);
+        for v in 1..=5 {
+            publisher.send_value(&v);
+        }
+        assert_eq!(*x.lock().unwrap(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn limited_demand_shared() {
+        let a: Shared<Publisher<u64>> = Publisher::new();
+        let b: Shared<Publisher<u64>> = Publisher::new();
+        let subscriber: Shared<Subscriber<u64>> = Subscriber::with_demand(Demand::max(3));
+        let x: Shared<Lock<Vec<u64>>> = Shared::new(Lock::new(vec![]));
+        let r = Shared::clone(&x);
+        let _sink = subscriber
+            .sink(move |v| r.lock().unwrap().push(*v));
+        let _a = a.receive_subscriber(&subscriber);
+        let _b = b.receive_subscriber(&subscriber);
+        for v in 1..=3 {
+            a.send_value(&v);
+            b.send_value(&(v * 10));
+        }
+        assert_eq!(*x.lock().unwrap(), vec![1, 10, 2]);
+        subscriber.request(Demand::max(1));
+        b.send_value(&40);
+        a.send_value(&4);
+        assert_eq!(*x.lock().unwrap(), vec![1, 10, 2, 40]);
+    }
+
+    #[test]
+    fn request_buffered() {
+        let publisher: Shared<Publisher<u64>> = Publisher::with_policy(BufferingPolicy::DropNewest(10));
+        let subscriber: Shared<Subscriber<u64>> = Subscriber::with_demand(Demand::nothing());
+        let x: Shared<Lock<Vec<u64>>> = Shared::new(Lock::new(vec![]));
+        let r = Shared::clone(&x);
+        let _sink = subscriber
+            .sink(move |v| r.lock().unwrap().push(*v));
+        let _subscription = publisher.receive_subscriber(&subscriber);
+        for v in 1..=5 {
+            publisher.send_value(&v);
+        }
+        assert!(x.lock().unwrap().is_empty());
+        subscriber.request(Demand::max(3));
+        assert_eq!(*x.lock().unwrap(), vec![1, 2, 3]);
+        subscriber.request(Demand::unlimited());
+        publisher.send_value(&6);
+        assert_eq!(*x.lock().unwrap(), vec![1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn completion_after_buffered() {
+        let publisher: Shared<Publisher<u64>> = Publisher::with_policy(BufferingPolicy::DropNewest(10));
+        let subscriber: Shared<Subscriber<u64>> = Subscriber::with_demand(Demand::nothing());
+        let x: Shared<Lock<Vec<u64>>> = Shared::new(Lock::new(vec![]));
+        let r = Shared::clone(&x);
+        let _sink = subscriber
+            .sink(move |v| r.lock().unwrap().push(*v));
+        let _subscription = publisher.receive_subscriber(&subscriber);
+        publisher.send_value(&1);
+        publisher.send_value(&2);
+        publisher.send_completion(&Completion::Finished);
+        subscriber.request(Demand::max(1));
+        assert!(!subscriber.is_completed());
+        subscriber.request(Demand::max(1));
+        assert_eq!(*x.lock().unwrap(), vec![1, 2]);
+        assert!(subscriber.is_completed());
+    }
+
+    #[test]
+    fn buffering_policy() {
+        let publisher: Shared<Publisher<u64>> = Publisher::with_policy(BufferingPolicy::DropOldest(2));
+        let subscriber: Shared<Subscriber<u64>> = Subscriber::with_demand(Demand::nothing());
+        let x: Shared<Lock<Vec<u64>>> = Shared::new(Lock::new(vec![]));
+        let r = Shared::clone(&x);
+        let _sink = subscriber
+            .sink(move |v| r.lock().unwrap().push(*v));
+        let _subscription = publisher.receive_subscriber(&subscriber);
+        for v in 1..=4 {
+            publisher.send_value(&v);
+        }
+        subscriber.request(Demand::unlimited());
+        assert_eq!(*x.lock().unwrap(), vec![3, 4]);
+        let subscriber: Shared<Subscriber<u64>> = Subscriber::with_demand(Demand::max(1));
+        let x: Shared<Lock<Vec<u64>>> = Shared::new(Lock::new(vec![]));
+        let r = Shared::clone(&x);
+        let _sink = subscriber
+            .sink(move |v| r.lock().unwrap().push(*v));
+        let publisher: Shared<Publisher<u64>> = Publisher::new();
+        let _subscription = publisher.receive_subscriber(&subscriber);
+        for v in 1..=4 {
+            publisher.send_value(&v);
+        }
+        subscriber.request(Demand::unlimited());
+        publisher.send_value(&5);
+        assert_eq!(*x.lock().unwrap(), vec![1, 5]);
+    }
+
+    #[test]
+    fn completion() {
+        let publisher: Shared<Publisher<u64, String>> = Publisher::new();
+        let subscriber: Shared<Subscriber<u64, String>> = Subscriber::new();
+        let x: Shared<Lock<Vec<u64>>> = Shared::new(Lock::new(vec![]));
+        let c: Shared<Lock<Vec<Completion<String>>>> = Shared::new(Lock::new(vec![]));
+        let r = Shared::clone(&x);
+        let _sink = subscriber
+            .sink(move |v| r.lock().unwrap().push(*v));
+        let r = Shared::clone(&c);
+        let _completion = subscriber
+            .sink_completion(move |v| r.lock().unwrap().push(v.clone()));
+        let _subscription = publisher.receive_subscriber(&subscriber);
+        publisher.send_value(&1);
+        publisher.send_completion(&Completion::Failure("failed".into()));
+        publisher.send_value(&2);
+        publisher.send_completion(&Completion::Finished);
+        assert_eq!(*x.lock().unwrap(), vec![1]);
+        assert_eq!(*c.lock().unwrap(), vec![Completion::Failure("failed".to_string())]);
+        assert!(publisher.is_completed());
+        assert!(subscriber.is_completed());
+    }
+
+    #[test]
+    fn nothing_after_completion() {
+        let publisher: Shared<Publisher<u64>> = Publisher::new();
+        let other: Shared<Publisher<u64>> = Publisher::new();
+        let subscriber: Shared<Subscriber<u64>> = Subscriber::new();
+        let x: Shared<Lock<Vec<u64>>> = Shared::new(Lock::new(vec![]));
+        let c: Shared<Lock<u64>> = Shared::new(Lock::new(0));
+        let r = Shared::clone(&x);
+        let _sink = subscriber
+            .map(|v| v * 10)
+            .sink(move |v| r.lock().unwrap().push(*v));
+        let r = Shared::clone(&c);
+        let _completion = subscriber
+            .sink_completion(move |_| *r.lock().unwrap() += 1);
+        let _subscription = publisher.receive_subscriber(&subscriber);
+        let _other = other.receive_subscriber(&subscriber);
+        other.send_value(&1);
+        publisher.send_completion(&Completion::Finished);
+        other.send_value(&2);
+        other.send_completion(&Completion::Finished);
+        assert_eq!(*x.lock().unwrap(), vec![10]);
+        assert_eq!(*c.lock().unwrap(), 1);
+        // late subscribers of a completed publisher receive only the completion
+        let late: Shared<Subscriber<u64>> = Subscriber::new();
+        let r = Shared::clone(&x);
+        let _sink = late
+            .sink(move |v| r.lock().unwrap().push(*v));
+        let r = Shared::clone(&c);
+        let _late_completion = late
+            .sink_completion(move |_| *r.lock().unwrap() += 1);
+        let _late = publisher.receive_subscriber(&late);
+        publisher.send_value(&3);
+        assert_eq!(*x.lock().unwrap(), vec![10]);
+        assert_eq!(*c.lock().unwrap(), 2);
+        assert!(late.is_completed());
+    }
+
+    #[test]
+    fn map_completion() {
+        let publisher: Shared<Publisher<u64, String>> = Publisher::new();
+        let subscriber: Shared<Subscriber<u64, String>> = Subscriber::new();
+        let c: Shared<Lock<Option<Completion<String>>>> = Shared::new(Lock::new(None));
+        let r = Shared::clone(&c);
+        let _completion = subscriber
+            .map(|v| v + 1)
+            .sink_completion(move |v| *r.lock().unwrap() = Some(v.clone()));
+        let _subscription = publisher.receive_subscriber(&subscriber);
+        publisher.send_completion(&Completion::Failure("failed".into()));
+        assert_eq!(*c.lock().unwrap(), Some(Completion::Failure("failed".to_string())));
+    }
+
+    #[test]
+    fn cancel_subscriber() {
+        let publisher: Shared<Publisher<u64>> = Publisher::new();
+        let subscriber: Shared<Subscriber<u64>> = Subscriber::new();
+        let x: Shared<Lock<u64>> = Shared::new(Lock::new(0));
+        let r = Shared::clone(&x);
+        let _sink = subscriber
+            .sink(move |v| *r.lock().unwrap() += *v);
+        let _subscription = publisher.receive_subscriber(&subscriber);
+        publisher.send_value(&1);
+        subscriber.cancel();
+        publisher.send_value(&10);
+        assert_eq!(*x.lock().unwrap(), 1);
+    }
+
+    fn feedback(publisher: &Shared<Publisher<u64>>, log: &Shared<Lock<Vec<(&'static str, u64)>>>) -> Vec<AnyCancellable> {
+        let a: Shared<Subscriber<u64>> = Subscriber::new();
+        let b: Shared<Subscriber<u64>> = Subscriber::new();
+        let a_log = Shared::clone(log);
+        let a_publisher = Shared::downgrade(publisher);
+        let b_log = Shared::clone(log);
+        vec![
+            a.sink(move |v| {
+                a_log.lock().unwrap().push(("a", *v));
+                if *v < 2 {
+                    a_publisher.upgrade().unwrap().send_value(&(v + 1));
+                }
+            }),
+            b.sink(move |v| b_log.lock().unwrap().push(("b", *v))),
+            publisher.receive_subscriber(&a),
+            publisher.receive_subscriber(&b),
+        ]
+    }
+
+    #[test]
+    fn reentrant_nested() {
+        let publisher: Shared<Publisher<u64>> = Publisher::new();
+        let log = Shared::new(Lock::new(vec![]));
+        let _cancellables = feedback(&publisher, &log);
+        publisher.send_value(&0);
+        assert_eq!(*log.lock().unwrap(), vec![("a", 0), ("a", 1), ("a", 2), ("b", 2), ("b", 1), ("b", 0)]);
+    }
+
+    #[test]
+    fn reentrant_enqueue() {
+        let publisher: Shared<Publisher<u64>> = Publisher::with_reentrancy(ReentrancyPolicy::Enqueue);
+        let log = Shared::new(Lock::new(vec![]));
+        let _cancellables = feedback(&publisher, &log);
+        publisher.send_value(&0);
+        assert_eq!(*log.lock().unwrap(), vec![("a", 0), ("b", 0), ("a", 1), ("b", 1), ("a", 2), ("b", 2)]);
+    }
+
+    #[test]
+    fn reentrant_completion() {
+        let publisher: Shared<Publisher<u64>> = Publisher::with_reentrancy(ReentrancyPolicy::Enqueue);
+        let subscriber: Shared<Subscriber<u64>> = Subscriber::new();
+        let values = Shared::new(Lock::new(vec![]));
+        let r = Shared::clone(&values);
+        let publisher_ref = Shared::downgrade(&publisher);
+        let _sink = subscriber.sink(move |v| {
+            r.lock().unwrap().push(*v);
+            let publisher = publisher_ref.upgrade().unwrap();
+            publisher.send_value(&(v + 1));
+            publisher.send_completion(&Completion::Finished);
+            publisher.send_value(&(v + 2));
+        });
+        let _subscription = publisher.receive_subscriber(&subscriber);
+        publisher.send_value(&0);
+        // the value enqueued before completion is still delivered, nothing after it
+        assert_eq!(*values.lock().unwrap(), vec![0, 1]);
+        assert!(subscriber.is_completed());
+    }
+
+    #[test]
+    fn property_accept_from_sink() {
+        let property = Property::new(0u64);
+        let property_ref = Shared::downgrade(&property);
+        let values = Shared::new(Lock::new(vec![]));
+        let r = Shared::clone(&values);
+        let _sink = property.subscriber().sink(move |v| {
+            r.lock().unwrap().push(*v);
+            if *v < 3 {
+                property_ref.upgrade().unwrap().accept(&(v + 1));
+            }
+        });
+        property.accept(&1);
+        assert_eq!(*values.lock().unwrap(), vec![1, 2, 3]);
+        assert_eq!(property.value(), 3);
+    }
+
+    #[test]
+    #[allow(clippy::arc_with_non_send_sync)]
+    fn cancel_from_sink() {
+        let publisher: Shared<Publisher<u64>> = Publisher::new();
+        let subscriber: Shared<Subscriber<u64>> = Subscriber::new();
+        let subscription: Shared<Lock<Option<AnyCancellable>>> = Shared::new(Lock::new(None));
+        let subscription_ref = Shared::clone(&subscription);
+        let x: Shared<Lock<u64>> = Shared::new(Lock::new(0));
+        let r = Shared::clone(&x);
+        let _sink = subscriber.sink(move |v| {
+            *r.lock().unwrap() += *v;
+            let cancellable = subscription_ref.lock().unwrap().take();
+            drop(cancellable);
+        });
+        *subscription.lock().unwrap() = Some(publisher.receive_subscriber(&subscriber));
+        publisher.send_value(&1);
+        publisher.send_value(&10);
+        assert_eq!(*x.lock().unwrap(), 1);
+        assert!(publisher.state.lock().unwrap().subscriptions.is_empty());
+    }
+
+    #[test]
+    #[allow(clippy::arc_with_non_send_sync)]
+    fn subscribe_from_sink() {
+        let publisher: Shared<Publisher<u64>> = Publisher::new();
+        let subscriber: Shared<Subscriber<u64>> = Subscriber::new();
+        let late: Shared<Subscriber<u64>> = Subscriber::new();
+        let x: Shared<Lock<u64>> = Shared::new(Lock::new(0));
+        let r = Shared::clone(&x);
+        let _late_sink = late.sink(move |v| *r.lock().unwrap() += *v);
+        let cancellables = Shared::new(Lock::new(vec![]));
+        let cancellables_ref = Shared::clone(&cancellables);
+        let publisher_ref = Shared::downgrade(&publisher);
+        let late_ref = Shared::clone(&late);
+        let subscriber_ref = Shared::downgrade(&subscriber);
+        let _sink = subscriber.sink(move |_| {
+            let publisher = publisher_ref.upgrade().unwrap();
+            let subscriber = subscriber_ref.upgrade().unwrap();
+            let mut cancellables = cancellables_ref.lock().unwrap();
+            cancellables.push(publisher.receive_subscriber(&late_ref));
+            cancellables.push(subscriber.sink(|_| {}));
+        });
+        let _subscription = publisher.receive_subscriber(&subscriber);
+        publisher.send_value(&1);
+        publisher.send_value(&10);
+        // the late subscriber joins from the next value on
+        assert_eq!(*x.lock().unwrap(), 10);
+    }
+}
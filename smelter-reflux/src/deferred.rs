@@ -0,0 +1,29 @@
+use std::convert::Infallible;
+
+use super::{AnyCancellable, Publish, Publisher, Shared, Subscriber, Threadsafe};
+
+type Factory<T, E> = Box<threadsafe_dyn!(Fn() -> Shared<Publisher<T, E>>)>;
+
+// builds a fresh upstream publisher for every subscriber, so that a failed one can be subscribed to again
+pub struct Deferred<T, E = Infallible> {
+    factory: Factory<T, E>,
+}
+
+impl<T, E> Deferred<T, E> where T: Threadsafe, T: 'static, E: Clone, E: Threadsafe, E: 'static {
+    pub fn new<F>(f: F) -> Shared<Self> where F: Fn() -> Shared<Publisher<T, E>>, F: Threadsafe, F: 'static {
+        let deferred = Self {
+            factory: Box::new(f),
+        };
+        Shared::new(deferred)
+    }
+
+    pub fn receive_subscriber(&self, subscriber: &Shared<Subscriber<T, E>>) -> AnyCancellable {
+        (self.factory)().receive_subscriber(subscriber)
+    }
+
+    pub fn subscribe(&self) -> Shared<Subscriber<T, E>> {
+        let subscriber = Subscriber::new();
+        subscriber.store(self.receive_subscriber(&subscriber));
+        subscriber
+    }
+}
@@ -0,0 +1,8 @@
+// `Rc` and `RefCell`, nothing is required to be `Send` or `Sync`
+#[macro_use]
+mod shared;
+// the same files make up the other flavours
+#[allow(clippy::duplicate_mod)]
+#[path = "core.rs"]
+mod reflux;
+pub use self::reflux::*;
@@ -0,0 +1,34 @@
+use std::cell::{BorrowMutError, RefCell, RefMut};
+
+pub use std::rc::{Rc as Shared, Weak as WeakShared};
+
+// mirrors the part of `Mutex` in use, a borrow that is already taken fails like a poisoned lock
+pub(crate) struct Lock<T> {
+    cell: RefCell<T>,
+}
+
+impl<T> Lock<T> {
+    pub(crate) fn new(v: T) -> Self {
+        Self {
+            cell: RefCell::new(v),
+        }
+    }
+
+    pub(crate) fn lock(&self) -> Result<RefMut<'_, T>, BorrowMutError> {
+        self.cell.try_borrow_mut()
+    }
+}
+
+// values are stored whole, so no borrow is held while something else reads
+pub(crate) fn read<T>(lock: &Lock<T>) -> T where T: Clone {
+    lock.cell.borrow().clone()
+}
+
+pub trait Threadsafe {}
+
+impl<T> Threadsafe for T where T: ?Sized {}
+
+macro_rules! threadsafe_dyn {
+    (send $($bounds:tt)*) => { dyn $($bounds)* };
+    ($($bounds:tt)*) => { dyn $($bounds)* };
+}
@@ -0,0 +1,280 @@
+use std::collections::VecDeque;
+
+use super::{Completion, Lock, Publish, Publisher, Shared, Subscriber, Threadsafe};
+use super::relay;
+
+impl<T, E> Subscriber<T, E> where T: Threadsafe, T: 'static, E: Clone, E: Threadsafe, E: 'static {
+    pub fn merge(self: &Shared<Self>, other: &Shared<Subscriber<T, E>>) -> Shared<Subscriber<T, E>> {
+        let (publisher, subscriber) = relay();
+        let finished = Shared::new(Lock::new(0));
+        for upstream in [self, other] {
+            let value_publisher = Shared::clone(&publisher);
+            subscriber.store(upstream.sink(move |v| value_publisher.send_value(v)));
+            subscriber.store(upstream.sink_completion(complete_all(&publisher, &finished, 2)));
+        }
+        subscriber
+    }
+
+    pub fn merge3(self: &Shared<Self>, b: &Shared<Subscriber<T, E>>, c: &Shared<Subscriber<T, E>>) -> Shared<Subscriber<T, E>> {
+        self.merge(b)
+            .merge(c)
+    }
+
+    pub fn merge4(self: &Shared<Self>, b: &Shared<Subscriber<T, E>>, c: &Shared<Subscriber<T, E>>, d: &Shared<Subscriber<T, E>>) -> Shared<Subscriber<T, E>> {
+        self.merge(b)
+            .merge(c)
+            .merge(d)
+    }
+
+    pub fn zip<U>(self: &Shared<Self>, other: &Shared<Subscriber<U, E>>) -> Shared<Subscriber<(T, U), E>> where T: Clone, U: Clone, U: Threadsafe, U: 'static {
+        let (publisher, subscriber) = relay();
+        let state: Shared<Lock<ZipState<T, U>>> = Shared::new(Lock::new(ZipState::new()));
+        let state_ref = Shared::clone(&state);
+        let value_publisher = Shared::clone(&publisher);
+        subscriber.store(self.sink(move |v| {
+            let pair = state_ref.lock()
+                .ok()
+                .and_then(|mut guard| {
+                    guard.left.push_back(v.clone());
+                    guard.next()
+                });
+            if let Some(pair) = pair {
+                value_publisher.send_value(&pair);
+            }
+        }));
+        let state_ref = Shared::clone(&state);
+        let value_publisher = Shared::clone(&publisher);
+        subscriber.store(other.sink(move |v| {
+            let pair = state_ref.lock()
+                .ok()
+                .and_then(|mut guard| {
+                    guard.right.push_back(v.clone());
+                    guard.next()
+                });
+            if let Some(pair) = pair {
+                value_publisher.send_value(&pair);
+            }
+        }));
+        // no more pairs can be made once either side finishes
+        subscriber.store(self.forward_completion(&publisher));
+        subscriber.store(other.forward_completion(&publisher));
+        subscriber
+    }
+
+    pub fn zip3<U, V>(self: &Shared<Self>, b: &Shared<Subscriber<U, E>>, c: &Shared<Subscriber<V, E>>) -> Shared<Subscriber<(T, U, V), E>> where T: Clone, U: Clone, U: Threadsafe, U: 'static, V: Clone, V: Threadsafe, V: 'static {
+        self.zip(b)
+            .zip(c)
+            .map(|((a, b), c)| (a.clone(), b.clone(), c.clone()))
+    }
+
+    #[allow(clippy::type_complexity)]
+    pub fn zip4<U, V, W>(self: &Shared<Self>, b: &Shared<Subscriber<U, E>>, c: &Shared<Subscriber<V, E>>, d: &Shared<Subscriber<W, E>>) -> Shared<Subscriber<(T, U, V, W), E>> where T: Clone, U: Clone, U: Threadsafe, U: 'static, V: Clone, V: Threadsafe, V: 'static, W: Clone, W: Threadsafe, W: 'static {
+        self.zip(b)
+            .zip(c)
+            .zip(d)
+            .map(|(((a, b), c), d)| (a.clone(), b.clone(), c.clone(), d.clone()))
+    }
+
+    pub fn combine_latest<U>(self: &Shared<Self>, other: &Shared<Subscriber<U, E>>) -> Shared<Subscriber<(T, U), E>> where T: Clone, U: Clone, U: Threadsafe, U: 'static {
+        let (publisher, subscriber) = relay();
+        let state: Shared<Lock<(Option<T>, Option<U>)>> = Shared::new(Lock::new((None, None)));
+        let state_ref = Shared::clone(&state);
+        let value_publisher = Shared::clone(&publisher);
+        subscriber.store(self.sink(move |v| {
+            let pair = state_ref.lock()
+                .ok()
+                .and_then(|mut guard| {
+                    guard.0 = Some(v.clone());
+                    latest(&guard)
+                });
+            if let Some(pair) = pair {
+                value_publisher.send_value(&pair);
+            }
+        }));
+        let state_ref = Shared::clone(&state);
+        let value_publisher = Shared::clone(&publisher);
+        subscriber.store(other.sink(move |v| {
+            let pair = state_ref.lock()
+                .ok()
+                .and_then(|mut guard| {
+                    guard.1 = Some(v.clone());
+                    latest(&guard)
+                });
+            if let Some(pair) = pair {
+                value_publisher.send_value(&pair);
+            }
+        }));
+        let finished = Shared::new(Lock::new(0));
+        subscriber.store(self.sink_completion(complete_all(&publisher, &finished, 2)));
+        subscriber.store(other.sink_completion(complete_all(&publisher, &finished, 2)));
+        subscriber
+    }
+
+    pub fn combine_latest3<U, V>(self: &Shared<Self>, b: &Shared<Subscriber<U, E>>, c: &Shared<Subscriber<V, E>>) -> Shared<Subscriber<(T, U, V), E>> where T: Clone, U: Clone, U: Threadsafe, U: 'static, V: Clone, V: Threadsafe, V: 'static {
+        self.combine_latest(b)
+            .combine_latest(c)
+            .map(|((a, b), c)| (a.clone(), b.clone(), c.clone()))
+    }
+
+    #[allow(clippy::type_complexity)]
+    pub fn combine_latest4<U, V, W>(self: &Shared<Self>, b: &Shared<Subscriber<U, E>>, c: &Shared<Subscriber<V, E>>, d: &Shared<Subscriber<W, E>>) -> Shared<Subscriber<(T, U, V, W), E>> where T: Clone, U: Clone, U: Threadsafe, U: 'static, V: Clone, V: Threadsafe, V: 'static, W: Clone, W: Threadsafe, W: 'static {
+        self.combine_latest(b)
+            .combine_latest(c)
+            .combine_latest(d)
+            .map(|(((a, b), c), d)| (a.clone(), b.clone(), c.clone(), d.clone()))
+    }
+}
+
+// finishes once every upstream has finished, a failure is forwarded right away
+fn complete_all<S, E>(publisher: &Shared<Publisher<S, E>>, finished: &Shared<Lock<usize>>, count: usize) -> impl Fn(&Completion<E>) where S: Threadsafe, S: 'static, E: Clone, E: Threadsafe, E: 'static {
+    let publisher = Shared::clone(publisher);
+    let finished = Shared::clone(finished);
+    move |completion| {
+        if let Completion::Finished = completion {
+            let all_finished = finished.lock()
+                .map(|mut guard| {
+                    *guard += 1;
+                    *guard == count
+                })
+                .unwrap_or(false);
+            if !all_finished {
+                return;
+            }
+        }
+        publisher.send_completion(completion);
+    }
+}
+
+fn latest<T, U>(state: &(Option<T>, Option<U>)) -> Option<(T, U)> where T: Clone, U: Clone {
+    match state {
+        (Some(left), Some(right)) => Some((left.clone(), right.clone())),
+        _ => None,
+    }
+}
+
+struct ZipState<T, U> {
+    left: VecDeque<T>,
+    right: VecDeque<U>,
+}
+
+impl<T, U> ZipState<T, U> {
+    fn new() -> Self {
+        Self {
+            left: VecDeque::new(),
+            right: VecDeque::new(),
+        }
+    }
+
+    fn next(&mut self) -> Option<(T, U)> {
+        if self.left.is_empty() || self.right.is_empty() {
+            return None;
+        }
+        let left = self.left.pop_front()?;
+        let right = self.right.pop_front()?;
+        Some((left, right))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+    use super::super::testing::*;
+
+    #[test]
+    fn merge() {
+        let a: Shared<Publisher<u64>> = Publisher::new();
+        let b: Shared<Publisher<u64>> = Publisher::new();
+        let c: Shared<Publisher<u64>> = Publisher::new();
+        let merged = a.subscribe().merge3(&b.subscribe(), &c.subscribe());
+        let recorded = TestSubscriber::new(&merged);
+        a.send_value(&1);
+        b.send_value(&2);
+        c.send_value(&3);
+        a.send_value(&4);
+        assert_eq!(recorded.values(), vec![1, 2, 3, 4]);
+        a.send_completion(&Completion::Finished);
+        b.send_completion(&Completion::Finished);
+        assert!(!merged.is_completed());
+        c.send_value(&5);
+        c.send_completion(&Completion::Finished);
+        assert_eq!(recorded.values(), vec![1, 2, 3, 4, 5]);
+        assert!(merged.is_completed());
+    }
+
+    #[test]
+    fn zip() {
+        let a: Shared<Publisher<u64>> = Publisher::new();
+        let b: Shared<Publisher<&str>> = Publisher::new();
+        let zipped = a.subscribe().zip(&b.subscribe());
+        let recorded = TestSubscriber::new(&zipped);
+        a.send_value(&1);
+        a.send_value(&2);
+        b.send_value(&"a");
+        a.send_value(&3);
+        b.send_value(&"b");
+        assert_eq!(recorded.values(), vec![(1, "a"), (2, "b")]);
+        b.send_completion(&Completion::Finished);
+        assert!(zipped.is_completed());
+    }
+
+    #[test]
+    fn zip4() {
+        let a: Shared<Publisher<u64>> = Publisher::new();
+        let b: Shared<Publisher<u64>> = Publisher::new();
+        let c: Shared<Publisher<u64>> = Publisher::new();
+        let d: Shared<Publisher<u64>> = Publisher::new();
+        let zipped = a.subscribe().zip4(&b.subscribe(), &c.subscribe(), &d.subscribe());
+        let recorded = TestSubscriber::new(&zipped);
+        for (i, publisher) in [&a, &b, &c, &d].iter().enumerate() {
+            publisher.send_value(&(i as u64));
+            publisher.send_value(&(i as u64 + 10));
+        }
+        assert_eq!(recorded.values(), vec![(0, 1, 2, 3), (10, 11, 12, 13)]);
+    }
+
+    #[test]
+    fn combine_latest() {
+        let a: Shared<Publisher<String>> = Publisher::new();
+        let b: Shared<Publisher<String>> = Publisher::new();
+        let enabled = a.subscribe()
+            .combine_latest(&b.subscribe())
+            .map(|(a, b)| !a.is_empty() && !b.is_empty());
+        let recorded = TestSubscriber::new(&enabled);
+        a.send_value(&"user".into());
+        assert!(recorded.values().is_empty());
+        b.send_value(&"".into());
+        b.send_value(&"secret".into());
+        a.send_value(&"".into());
+        assert_eq!(recorded.values(), vec![false, true, false]);
+        a.send_completion(&Completion::Finished);
+        assert!(!enabled.is_completed());
+        b.send_completion(&Completion::Finished);
+        assert!(enabled.is_completed());
+    }
+
+    #[test]
+    fn combine_latest3() {
+        let a: Shared<Publisher<u64>> = Publisher::new();
+        let b: Shared<Publisher<u64>> = Publisher::new();
+        let c: Shared<Publisher<u64>> = Publisher::new();
+        let combined = a.subscribe().combine_latest3(&b.subscribe(), &c.subscribe());
+        let recorded = TestSubscriber::new(&combined);
+        a.send_value(&1);
+        b.send_value(&2);
+        c.send_value(&3);
+        b.send_value(&4);
+        assert_eq!(recorded.values(), vec![(1, 2, 3), (1, 4, 3)]);
+    }
+
+    #[test]
+    fn failure() {
+        let a: Shared<Publisher<u64, String>> = Publisher::new();
+        let b: Shared<Publisher<u64, String>> = Publisher::new();
+        let merged = a.subscribe().merge(&b.subscribe());
+        let completion = Shared::new(Lock::new(None));
+        let r = Shared::clone(&completion);
+        let _completion = merged.sink_completion(move |v| *r.lock().unwrap() = Some(v.clone()));
+        b.send_completion(&Completion::Failure("failed".into()));
+        assert_eq!(*completion.lock().unwrap(), Some(Completion::Failure("failed".to_string())));
+    }
+}
@@ -0,0 +1,283 @@
+use std::fmt::Debug;
+
+use super::{AnyCancellable, Completion, Lock, Publish, Shared, Sink, Subscriber, SubscriptionSink, Threadsafe};
+use super::relay;
+
+// callbacks for what happens to a subscriber, see `handle_events`
+pub struct EventHandlers<T, E> {
+    subscription: Option<SubscriptionSink>,
+    value: Option<Sink<T>>,
+    completion: Option<Sink<Completion<E>>>,
+    cancel: Option<SubscriptionSink>,
+}
+
+impl<T, E> EventHandlers<T, E> {
+    pub fn new() -> Self {
+        Self {
+            subscription: None,
+            value: None,
+            completion: None,
+            cancel: None,
+        }
+    }
+
+    pub fn on_subscription<F>(mut self, f: F) -> Self where F: Fn(), F: Threadsafe, F: 'static {
+        self.subscription = Some(Shared::new(f));
+        self
+    }
+
+    pub fn on_value<F>(mut self, f: F) -> Self where F: Fn(&T), F: Threadsafe, F: 'static {
+        self.value = Some(Shared::new(f));
+        self
+    }
+
+    pub fn on_completion<F>(mut self, f: F) -> Self where F: Fn(&Completion<E>), F: Threadsafe, F: 'static {
+        self.completion = Some(Shared::new(f));
+        self
+    }
+
+    // the downstream was cancelled or dropped before completing
+    pub fn on_cancel<F>(mut self, f: F) -> Self where F: Fn(), F: Threadsafe, F: 'static {
+        self.cancel = Some(Shared::new(f));
+        self
+    }
+}
+
+impl<T, E> Default for EventHandlers<T, E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// conditions to stop in the debugger on, see `breakpoint`
+pub struct Breakpoints<T, E> {
+    handlers: EventHandlers<T, E>,
+}
+
+impl<T, E> Breakpoints<T, E> {
+    pub fn new() -> Self {
+        Self {
+            handlers: EventHandlers::new(),
+        }
+    }
+
+    pub fn on_subscription<F>(mut self, f: F) -> Self where F: Fn() -> bool, F: Threadsafe, F: 'static {
+        self.handlers = self.handlers.on_subscription(move || if f() { trap() });
+        self
+    }
+
+    pub fn on_value<F>(mut self, f: F) -> Self where F: Fn(&T) -> bool, F: Threadsafe, F: 'static {
+        self.handlers = self.handlers.on_value(move |v| if f(v) { trap() });
+        self
+    }
+
+    pub fn on_completion<F>(mut self, f: F) -> Self where F: Fn(&Completion<E>) -> bool, F: Threadsafe, F: 'static {
+        self.handlers = self.handlers.on_completion(move |v| if f(v) { trap() });
+        self
+    }
+}
+
+impl<T, E> Default for Breakpoints<T, E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, E> Subscriber<T, E> where T: Threadsafe, T: 'static, E: Clone, E: Threadsafe, E: 'static {
+    // passes everything through unchanged, running the handlers on the way
+    pub fn handle_events(self: &Shared<Self>, handlers: EventHandlers<T, E>) -> Shared<Subscriber<T, E>> {
+        let (publisher, subscriber) = relay();
+        let EventHandlers { subscription, value, completion, cancel } = handlers;
+        if let Some(f) = subscription {
+            subscriber.store(self.sink_subscription(move || f()));
+        }
+        let completed = Shared::new(Lock::new(false));
+        let completed_ref = Shared::clone(&completed);
+        let completion_publisher = Shared::clone(&publisher);
+        subscriber.store(self.sink_completion(move |v| {
+            if let Ok(mut guard) = completed_ref.lock() {
+                *guard = true;
+            }
+            if let Some(f) = completion.as_ref() {
+                f(v);
+            }
+            completion_publisher.send_completion(v);
+        }));
+        subscriber.store(self.sink(move |v| {
+            if let Some(f) = value.as_ref() {
+                f(v);
+            }
+            publisher.send_value(v);
+        }));
+        if let Some(f) = cancel {
+            subscriber.store(AnyCancellable::new(move || {
+                let completed = completed.lock()
+                    .map(|guard| *guard)
+                    .unwrap_or(true);
+                if !completed {
+                    f();
+                }
+            }));
+        }
+        subscriber
+    }
+
+    // stops in the debugger on the events the conditions hold for, passing everything through otherwise
+    pub fn breakpoint(self: &Shared<Self>, breakpoints: Breakpoints<T, E>) -> Shared<Subscriber<T, E>> {
+        self.handle_events(breakpoints.handlers)
+    }
+
+    pub fn breakpoint_on_error(self: &Shared<Self>) -> Shared<Subscriber<T, E>> {
+        self.breakpoint(Breakpoints::new().on_completion(|completion| matches!(completion, Completion::Failure(_))))
+    }
+
+    // logs every event prefixed with `prefix`, to the console on the web and to stderr elsewhere
+    pub fn print(self: &Shared<Self>, prefix: &str) -> Shared<Subscriber<T, E>> where T: Debug, E: Debug {
+        let prefix = prefix.to_string();
+        let (a, b, c, d) = (prefix.clone(), prefix.clone(), prefix.clone(), prefix);
+        let handlers = EventHandlers::new()
+            .on_subscription(move || log(&format!("{}: receive subscription", a)))
+            .on_value(move |v| log(&format!("{}: receive value: ({:?})", b, v)))
+            .on_completion(move |completion| match completion {
+                Completion::Finished => log(&format!("{}: receive finished", c)),
+                Completion::Failure(e) => log(&format!("{}: receive failure: ({:?})", c, e)),
+            })
+            .on_cancel(move || log(&format!("{}: receive cancel", d)));
+        self.handle_events(handlers)
+    }
+}
+
+// raises the trap a debugger stops at, without one attached the process ends
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn trap() {
+    unsafe { std::arch::asm!("int3") }
+}
+
+#[cfg(target_arch = "aarch64")]
+fn trap() {
+    unsafe { std::arch::asm!("brk #0xf000") }
+}
+
+// wasm has no trap to resume from, the panic stops at exceptions in the browser
+#[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
+fn trap() {
+    panic!("breakpoint");
+}
+
+#[cfg(target_arch = "wasm32")]
+fn log(line: &str) {
+    web_sys::console::log_1(&line.into());
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn log(line: &str) {
+    eprintln!("{}", line);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+
+    #[test]
+    fn handle_events() {
+        let log = Shared::new(Lock::new(vec![]));
+        let (a, b, c, d) = (Shared::clone(&log), Shared::clone(&log), Shared::clone(&log), Shared::clone(&log));
+        let publisher: Shared<Publisher<u64>> = Publisher::new();
+        let subscriber: Shared<Subscriber<u64>> = Subscriber::new();
+        let handled = subscriber.handle_events(EventHandlers::new()
+            .on_subscription(move || a.lock().unwrap().push("subscription".to_string()))
+            .on_value(move |v| b.lock().unwrap().push(format!("value {}", v)))
+            .on_completion(move |v| c.lock().unwrap().push(format!("completion {:?}", v)))
+            .on_cancel(move || d.lock().unwrap().push("cancel".to_string())));
+        let _subscription = publisher.receive_subscriber(&subscriber);
+        publisher.send_value(&1);
+        publisher.send_completion(&Completion::Finished);
+        drop(handled);
+        assert_eq!(*log.lock().unwrap(), vec!["subscription", "value 1", "completion Finished"]);
+    }
+
+    #[test]
+    fn handle_cancel() {
+        let cancelled = Shared::new(Lock::new(false));
+        let r = Shared::clone(&cancelled);
+        let publisher: Shared<Publisher<u64>> = Publisher::new();
+        let handled = publisher.subscribe()
+            .handle_events(EventHandlers::new().on_cancel(move || *r.lock().unwrap() = true));
+        publisher.send_value(&1);
+        assert!(!*cancelled.lock().unwrap());
+        handled.cancel();
+        assert!(*cancelled.lock().unwrap());
+    }
+
+    #[test]
+    fn breakpoint() {
+        // the conditions are checked on every event, none of them holds here
+        let checked = Shared::new(Lock::new(vec![]));
+        let (a, b, c) = (Shared::clone(&checked), Shared::clone(&checked), Shared::clone(&checked));
+        let publisher: Shared<Publisher<u64>> = Publisher::new();
+        let subscriber: Shared<Subscriber<u64>> = Subscriber::new();
+        let stopped = subscriber.breakpoint(Breakpoints::new()
+            .on_subscription(move || {
+                a.lock().unwrap().push("subscription".to_string());
+                false
+            })
+            .on_value(move |v| {
+                b.lock().unwrap().push(format!("value {}", v));
+                *v > 10
+            })
+            .on_completion(move |v| {
+                c.lock().unwrap().push(format!("completion {:?}", v));
+                matches!(v, Completion::Failure(_))
+            }));
+        let values = Shared::new(Lock::new(vec![]));
+        let r = Shared::clone(&values);
+        let _sink = stopped.sink(move |v| r.lock().unwrap().push(*v));
+        let _subscription = publisher.receive_subscriber(&subscriber);
+        publisher.send_value(&1);
+        publisher.send_completion(&Completion::Finished);
+        assert_eq!(*checked.lock().unwrap(), vec!["subscription", "value 1", "completion Finished"]);
+        assert_eq!(*values.lock().unwrap(), vec![1]);
+        assert!(stopped.is_completed());
+    }
+
+    #[test]
+    fn print() {
+        let publisher: Shared<Publisher<u64, String>> = Publisher::new();
+        let printed = publisher.subscribe()
+            .print("numbers");
+        let values = Shared::new(Lock::new(vec![]));
+        let r = Shared::clone(&values);
+        let _sink = printed.sink(move |v| r.lock().unwrap().push(*v));
+        publisher.send_value(&1);
+        publisher.send_completion(&Completion::Failure("failed".into()));
+        assert_eq!(*values.lock().unwrap(), vec![1]);
+        assert!(printed.is_completed());
+    }
+
+    #[test]
+    fn trace() {
+        // other tests may add unrelated nodes meanwhile, the graph is kept until every guard is dropped
+        let tracing = enable_tracing();
+        let publisher: Shared<Publisher<u64>> = Publisher::new();
+        let subscriber = publisher.subscribe();
+        let mapped = subscriber.map(|v| v + 1);
+        let _sink = mapped.sink(|_| {});
+        publisher.send_value(&1);
+        publisher.send_value(&2);
+        let graph = trace_graph();
+        drop(tracing);
+        let publisher_id = &*publisher as *const Publisher<u64> as usize;
+        let subscriber_id = &*subscriber as *const Subscriber<u64> as usize;
+        let mapped_id = &*mapped as *const Subscriber<u64> as usize;
+        assert_eq!(graph.node(publisher_id).map(|node| (node.kind, node.values)), Some((TraceNodeKind::Publisher, 2)));
+        assert_eq!(graph.node(subscriber_id).map(|node| node.values), Some(2));
+        // the publisher feeds the subscriber, whose sink feeds the publisher of `map`, which feeds `mapped`
+        let relay_id = graph.edges_from(subscriber_id)
+            .find(|edge| edge.kind == TraceEdgeKind::Sink)
+            .map(|edge| edge.to)
+            .unwrap();
+        assert!(graph.edges_from(publisher_id).any(|edge| edge.to == subscriber_id && edge.kind == TraceEdgeKind::Subscription));
+        assert!(graph.edges_from(relay_id).any(|edge| edge.to == mapped_id && edge.kind == TraceEdgeKind::Subscription));
+        assert!(graph.to_dot().contains(&format!("n{} -> n{} [style=solid];", publisher_id, subscriber_id)));
+    }
+}
@@ -0,0 +1,319 @@
+use std::convert::Infallible;
+
+use super::{AnyCancellable, Completion, Deferred, Lock, Publish, Publisher, Shared, Subscriber, Threadsafe};
+use super::relay;
+
+impl<T, E> Subscriber<T, E> where T: Threadsafe, T: 'static, E: Clone, E: Threadsafe, E: 'static {
+    // an error returned by the transform fails the downstream, later values are ignored
+    pub fn try_map<F, S>(self: &Shared<Self>, f: F) -> Shared<Subscriber<S, E>> where F: Fn(&T) -> Result<S, E>, F: Threadsafe, F: 'static, S: Threadsafe, S: 'static {
+        let (publisher, subscriber) = relay();
+        subscriber.store(self.forward_completion(&publisher));
+        subscriber.store(self.sink(move |v| {
+            match f(v) {
+                Ok(v) => publisher.send_value(&v),
+                Err(e) => publisher.send_completion(&Completion::Failure(e)),
+            }
+        }));
+        subscriber
+    }
+
+    pub fn map_error<F, U>(self: &Shared<Self>, f: F) -> Shared<Subscriber<T, U>> where F: Fn(&E) -> U, F: Threadsafe, F: 'static, U: Clone, U: Threadsafe, U: 'static {
+        let (publisher, subscriber) = relay();
+        let completion_publisher = Shared::clone(&publisher);
+        subscriber.store(self.sink_completion(move |completion| {
+            let completion = match completion {
+                Completion::Finished => Completion::Finished,
+                Completion::Failure(e) => Completion::Failure(f(e)),
+            };
+            completion_publisher.send_completion(&completion);
+        }));
+        subscriber.store(self.sink(move |v| publisher.send_value(v)));
+        subscriber
+    }
+
+    // a failure becomes the given value followed by finishing
+    pub fn replace_error(self: &Shared<Self>, v: T) -> Shared<Subscriber<T, Infallible>> {
+        let (publisher, subscriber) = relay();
+        let completion_publisher = Shared::clone(&publisher);
+        subscriber.store(self.sink_completion(move |completion| {
+            if let Completion::Failure(_) = completion {
+                completion_publisher.send_value(&v);
+            }
+            completion_publisher.send_completion(&Completion::Finished);
+        }));
+        subscriber.store(self.sink(move |v| publisher.send_value(v)));
+        subscriber
+    }
+
+    // on failure the downstream continues with the publisher made from the error
+    #[allow(clippy::arc_with_non_send_sync)]
+    pub fn catch<F, U>(self: &Shared<Self>, f: F) -> Shared<Subscriber<T, U>> where F: Fn(&E) -> Shared<Publisher<T, U>>, F: Threadsafe, F: 'static, U: Clone, U: Threadsafe, U: 'static {
+        let (publisher, subscriber) = relay();
+        // the subscription to the fallback belongs to the downstream rather than the completed upstream
+        let fallback: Shared<Lock<Vec<AnyCancellable>>> = Shared::new(Lock::new(vec![]));
+        let fallback_ref = Shared::clone(&fallback);
+        let completion_publisher = Shared::clone(&publisher);
+        subscriber.store(self.sink_completion(move |completion| {
+            let e = match completion {
+                Completion::Finished => {
+                    completion_publisher.send_completion(&Completion::Finished);
+                    return
+                },
+                Completion::Failure(e) => e,
+            };
+            let fallback_publisher = f(e);
+            let inner = Subscriber::new();
+            let mut cancellables = vec![];
+            inner.bind(&completion_publisher)
+                .store_in(&mut cancellables);
+            inner.forward_completion(&completion_publisher)
+                .store_in(&mut cancellables);
+            fallback_publisher.receive_subscriber(&inner)
+                .store_in(&mut cancellables);
+            if let Ok(mut guard) = fallback_ref.lock() {
+                guard.extend(cancellables);
+            }
+        }));
+        subscriber.store(self.sink(move |v| publisher.send_value(v)));
+        subscriber.store(AnyCancellable::new(move || drop(fallback)));
+        subscriber
+    }
+}
+
+impl<T, E> Deferred<T, E> where T: Threadsafe, T: 'static, E: Clone, E: Threadsafe, E: 'static {
+    // subscribes to a fresh upstream after each failure, up to `count` times before failing downstream
+    pub fn retry(self: &Shared<Self>, count: usize) -> Shared<Subscriber<T, E>> {
+        let (publisher, subscriber) = relay();
+        let retry = Shared::new(Retry {
+            deferred: Shared::clone(self),
+            publisher,
+            state: Lock::new(RetryState {
+                remaining: count,
+                generation: 0,
+                attempt: vec![],
+            }),
+        });
+        retry.attempt();
+        subscriber.store(AnyCancellable::new(move || retry.cancel()));
+        subscriber
+    }
+}
+
+struct Retry<T, E> {
+    deferred: Shared<Deferred<T, E>>,
+    publisher: Shared<Publisher<T, E>>,
+    state: Lock<RetryState>,
+}
+
+struct RetryState {
+    remaining: usize,
+    // counts the attempts, one failing synchronously starts the next before its own is stored
+    generation: u64,
+    attempt: Vec<AnyCancellable>,
+}
+
+impl<T, E> Retry<T, E> where T: Threadsafe, T: 'static, E: Clone, E: Threadsafe, E: 'static {
+    fn attempt(self: &Shared<Self>) {
+        let generation = self.state.lock()
+            .map(|mut guard| {
+                guard.generation += 1;
+                guard.generation
+            })
+            .unwrap_or_default();
+        let inner = Subscriber::new();
+        let mut cancellables = vec![];
+        inner.bind(&self.publisher)
+            .store_in(&mut cancellables);
+        let retry = Shared::downgrade(self);
+        inner.sink_completion(move |completion| {
+            if let Some(retry) = retry.upgrade() {
+                retry.receive_completion(completion);
+            }
+        })
+        .store_in(&mut cancellables);
+        self.deferred.receive_subscriber(&inner)
+            .store_in(&mut cancellables);
+        // the failed attempt is disposed once replaced, or this one if a later attempt has started meanwhile
+        let failed = self.state.lock()
+            .map(|mut guard| {
+                if guard.generation == generation {
+                    std::mem::replace(&mut guard.attempt, cancellables)
+                } else {
+                    cancellables
+                }
+            })
+            .unwrap_or_default();
+        drop(failed);
+    }
+
+    fn receive_completion(self: &Shared<Self>, completion: &Completion<E>) {
+        if let Completion::Failure(_) = completion {
+            let retrying = self.state.lock()
+                .map(|mut guard| {
+                    let retrying = guard.remaining > 0;
+                    guard.remaining = guard.remaining.saturating_sub(1);
+                    retrying
+                })
+                .unwrap_or(false);
+            if retrying {
+                self.attempt();
+                return;
+            }
+        }
+        self.publisher.send_completion(completion);
+    }
+
+    fn cancel(&self) {
+        let attempt = self.state.lock()
+            .map(|mut guard| std::mem::take(&mut guard.attempt))
+            .unwrap_or_default();
+        drop(attempt);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use super::super::*;
+
+    #[test]
+    fn try_map() {
+        let scheduler = ImmediateScheduler::new();
+        let publisher: Shared<Publisher<&'static str, String>> = Publisher::new();
+        let parsed = publisher.subscribe()
+            .try_map(|v| v.parse::<u64>().map_err(|_| format!("not a number: {}", v)))
+            .map(|v| v * 2);
+        let recorded = parsed.record(&scheduler);
+        publisher.send_value(&"1");
+        publisher.send_value(&"x");
+        publisher.send_value(&"2");
+        assert_eq!(recorded.values(), vec![2]);
+        assert_eq!(recorded.completion(), Some(Completion::Failure("not a number: x".to_string())));
+    }
+
+    #[test]
+    fn map_error() {
+        let scheduler = ImmediateScheduler::new();
+        let publisher: Shared<Publisher<u64, u64>> = Publisher::new();
+        let mapped = publisher.subscribe()
+            .map_error(|e| format!("code {}", e));
+        let recorded = mapped.record(&scheduler);
+        publisher.send_value(&1);
+        publisher.send_completion(&Completion::Failure(404));
+        assert_eq!(recorded.values(), vec![1]);
+        assert_eq!(recorded.completion(), Some(Completion::Failure("code 404".to_string())));
+    }
+
+    #[test]
+    fn replace_error() {
+        let scheduler = ImmediateScheduler::new();
+        let publisher: Shared<Publisher<u64, String>> = Publisher::new();
+        let replaced = publisher.subscribe()
+            .replace_error(0);
+        let recorded = replaced.record(&scheduler);
+        publisher.send_value(&1);
+        publisher.send_completion(&Completion::Failure("failed".into()));
+        assert_eq!(recorded.values(), vec![1, 0]);
+        assert_eq!(recorded.completion(), Some(Completion::Finished));
+    }
+
+    #[test]
+    fn catch() {
+        let scheduler = ImmediateScheduler::new();
+        let publisher: Shared<Publisher<u64, String>> = Publisher::new();
+        let fallback: Shared<Publisher<u64, Infallible>> = Publisher::new();
+        let fallback_ref = Shared::clone(&fallback);
+        let caught = publisher.subscribe()
+            .catch(move |_| Shared::clone(&fallback_ref));
+        let recorded = caught.record(&scheduler);
+        publisher.send_value(&1);
+        fallback.send_value(&100);
+        publisher.send_completion(&Completion::Failure("failed".into()));
+        fallback.send_value(&2);
+        assert_eq!(recorded.completion(), None);
+        fallback.send_completion(&Completion::Finished);
+        assert_eq!(recorded.values(), vec![1, 2]);
+        assert_eq!(recorded.completion(), Some(Completion::Finished));
+    }
+
+    // fails on the first `failures` attempts, then sends the attempt number and finishes
+    fn flaky(scheduler: &Shared<VirtualTimeScheduler>, failures: u64) -> (Shared<Deferred<u64, String>>, Shared<Lock<u64>>) {
+        let attempts = Shared::new(Lock::new(0));
+        let attempts_ref = Shared::clone(&attempts);
+        let scheduler = Shared::clone(scheduler);
+        let deferred = Deferred::new(move || {
+            let publisher: Shared<Publisher<u64, String>> = Publisher::new();
+            let attempt = {
+                let mut attempts = attempts_ref.lock().unwrap();
+                *attempts += 1;
+                *attempts
+            };
+            let publisher_ref = Shared::clone(&publisher);
+            scheduler.schedule(Box::new(move || {
+                if attempt <= failures {
+                    publisher_ref.send_completion(&Completion::Failure(format!("attempt {} failed", attempt)));
+                } else {
+                    publisher_ref.send_value(&attempt);
+                    publisher_ref.send_completion(&Completion::Finished);
+                }
+            }));
+            publisher
+        });
+        (deferred, attempts)
+    }
+
+    #[test]
+    fn retry() {
+        let scheduler = VirtualTimeScheduler::new();
+        let (deferred, attempts) = flaky(&scheduler, 2);
+        let retried = deferred.retry(2);
+        let recorded = retried.record(&scheduler);
+        scheduler.run();
+        assert_eq!(*attempts.lock().unwrap(), 3);
+        assert_eq!(recorded.values(), vec![3]);
+        assert_eq!(recorded.completion(), Some(Completion::Finished));
+    }
+
+    #[test]
+    fn retry_exhausted() {
+        let scheduler = VirtualTimeScheduler::new();
+        let (deferred, attempts) = flaky(&scheduler, 2);
+        let retried = deferred.retry(1);
+        let recorded = retried.record(&scheduler);
+        scheduler.run();
+        assert_eq!(*attempts.lock().unwrap(), 2);
+        assert!(recorded.values().is_empty());
+        assert_eq!(recorded.completion(), Some(Completion::Failure("attempt 2 failed".to_string())));
+    }
+
+    #[test]
+    fn retry_synchronous() {
+        let scheduler = ImmediateScheduler::new();
+        let succeeding: Shared<Publisher<u64, String>> = Publisher::new();
+        let succeeding_ref = Shared::clone(&succeeding);
+        let attempts = Shared::new(Lock::new(0));
+        let attempts_ref = Shared::clone(&attempts);
+        // the first two attempts have already failed by the time they are subscribed to
+        let deferred = Deferred::new(move || {
+            let attempt = {
+                let mut attempts = attempts_ref.lock().unwrap();
+                *attempts += 1;
+                *attempts
+            };
+            if attempt < 3 {
+                let publisher = Publisher::new();
+                publisher.send_completion(&Completion::Failure(format!("attempt {} failed", attempt)));
+                return publisher;
+            }
+            Shared::clone(&succeeding_ref)
+        });
+        let retried = deferred.retry(2);
+        let recorded = retried.record(&scheduler);
+        assert_eq!(*attempts.lock().unwrap(), 3);
+        succeeding.send_value(&3);
+        succeeding.send_completion(&Completion::Finished);
+        assert_eq!(recorded.values(), vec![3]);
+        assert_eq!(recorded.completion(), Some(Completion::Finished));
+    }
+}
@@ -0,0 +1,186 @@
+use super::{Completion, Lock, Publish, Shared, Subscriber, Threadsafe};
+use super::relay;
+
+impl<T, E> Subscriber<T, E> where T: Threadsafe, T: 'static, E: Clone, E: Threadsafe, E: 'static {
+    pub fn filter<F>(self: &Shared<Self>, f: F) -> Shared<Subscriber<T, E>> where F: Fn(&T) -> bool, F: Threadsafe, F: 'static {
+        let (publisher, subscriber) = relay();
+        subscriber.store(self.forward_completion(&publisher));
+        subscriber.store(self.sink(move |v| {
+            if f(v) {
+                publisher.send_value(v);
+            }
+        }));
+        subscriber
+    }
+
+    pub fn distinct_until_changed(self: &Shared<Self>) -> Shared<Subscriber<T, E>> where T: PartialEq, T: Clone {
+        let last: Lock<Option<T>> = Lock::new(None);
+        self.filter(move |v| {
+            let Ok(mut guard) = last.lock() else {
+                return false
+            };
+            if guard.as_ref() == Some(v) {
+                false
+            } else {
+                *guard = Some(v.clone());
+                true
+            }
+        })
+    }
+
+    pub fn take(self: &Shared<Self>, count: u64) -> Shared<Subscriber<T, E>> {
+        let (publisher, subscriber) = relay();
+        if count == 0 {
+            publisher.send_completion(&Completion::Finished);
+            return subscriber;
+        }
+        subscriber.store(self.forward_completion(&publisher));
+        let taken = Lock::new(0);
+        subscriber.store(self.sink(move |v| {
+            let taken = taken.lock()
+                .ok()
+                .filter(|guard| **guard < count)
+                .map(|mut guard| {
+                    *guard += 1;
+                    *guard
+                });
+            let Some(taken) = taken else {
+                return
+            };
+            publisher.send_value(v);
+            if taken == count {
+                publisher.send_completion(&Completion::Finished);
+            }
+        }));
+        subscriber
+    }
+
+    pub fn skip(self: &Shared<Self>, count: u64) -> Shared<Subscriber<T, E>> {
+        let skipped = Lock::new(0);
+        self.filter(move |_| {
+            let Ok(mut guard) = skipped.lock() else {
+                return false
+            };
+            if *guard < count {
+                *guard += 1;
+                false
+            } else {
+                true
+            }
+        })
+    }
+
+    pub fn first(self: &Shared<Self>) -> Shared<Subscriber<T, E>> {
+        self.take(1)
+    }
+
+    pub fn last(self: &Shared<Self>) -> Shared<Subscriber<T, E>> where T: Clone {
+        let (publisher, subscriber) = relay();
+        let last: Shared<Lock<Option<T>>> = Shared::new(Lock::new(None));
+        let last_ref = Shared::clone(&last);
+        subscriber.store(self.sink(move |v| {
+            if let Ok(mut guard) = last_ref.lock() {
+                *guard = Some(v.clone());
+            }
+        }));
+        subscriber.store(self.sink_completion(move |completion| {
+            if let Completion::Finished = completion {
+                let v = last.lock()
+                    .ok()
+                    .and_then(|mut guard| guard.take());
+                if let Some(v) = v {
+                    publisher.send_value(&v);
+                }
+            }
+            publisher.send_completion(completion);
+        }));
+        subscriber
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+    use super::super::testing::*;
+
+    #[test]
+    fn filter() {
+        let publisher: Shared<Publisher<u64>> = Publisher::new();
+        let subscriber: Shared<Subscriber<u64>> = Subscriber::new();
+        let recorded = TestSubscriber::new(&subscriber.filter(|v| v % 2 == 0));
+        let _subscription = publisher.receive_subscriber(&subscriber);
+        for v in 1..=6 {
+            publisher.send_value(&v);
+        }
+        assert_eq!(recorded.values(), vec![2, 4, 6]);
+    }
+
+    #[test]
+    fn distinct_until_changed() {
+        let publisher: Shared<Publisher<u64>> = Publisher::new();
+        let subscriber: Shared<Subscriber<u64>> = Subscriber::new();
+        let recorded = TestSubscriber::new(&subscriber.distinct_until_changed());
+        let _subscription = publisher.receive_subscriber(&subscriber);
+        for v in [1, 1, 2, 2, 2, 1, 3, 3] {
+            publisher.send_value(&v);
+        }
+        assert_eq!(recorded.values(), vec![1, 2, 1, 3]);
+    }
+
+    #[test]
+    fn take() {
+        let publisher: Shared<Publisher<u64>> = Publisher::new();
+        let subscriber: Shared<Subscriber<u64>> = Subscriber::new();
+        let taken = subscriber.take(2);
+        let recorded = TestSubscriber::new(&taken);
+        let _subscription = publisher.receive_subscriber(&subscriber);
+        for v in 1..=4 {
+            publisher.send_value(&v);
+        }
+        assert_eq!(recorded.values(), vec![1, 2]);
+        assert!(taken.is_completed());
+        assert!(subscriber.take(0).is_completed());
+    }
+
+    #[test]
+    fn skip() {
+        let publisher: Shared<Publisher<u64>> = Publisher::new();
+        let subscriber: Shared<Subscriber<u64>> = Subscriber::new();
+        let recorded = TestSubscriber::new(&subscriber.skip(2));
+        let _subscription = publisher.receive_subscriber(&subscriber);
+        for v in 1..=4 {
+            publisher.send_value(&v);
+        }
+        assert_eq!(recorded.values(), vec![3, 4]);
+    }
+
+    #[test]
+    fn first() {
+        let publisher: Shared<Publisher<u64>> = Publisher::new();
+        let subscriber: Shared<Subscriber<u64>> = Subscriber::new();
+        let first = subscriber.first();
+        let recorded = TestSubscriber::new(&first);
+        let _subscription = publisher.receive_subscriber(&subscriber);
+        for v in 1..=3 {
+            publisher.send_value(&v);
+        }
+        assert_eq!(recorded.values(), vec![1]);
+        assert!(first.is_completed());
+    }
+
+    #[test]
+    fn last() {
+        let publisher: Shared<Publisher<u64>> = Publisher::new();
+        let subscriber: Shared<Subscriber<u64>> = Subscriber::new();
+        let last = subscriber.last();
+        let recorded = TestSubscriber::new(&last);
+        let _subscription = publisher.receive_subscriber(&subscriber);
+        for v in 1..=3 {
+            publisher.send_value(&v);
+        }
+        assert!(recorded.values().is_empty());
+        publisher.send_completion(&Completion::Finished);
+        assert_eq!(recorded.values(), vec![3]);
+        assert!(last.is_completed());
+    }
+}
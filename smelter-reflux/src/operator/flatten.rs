@@ -0,0 +1,309 @@
+use std::collections::VecDeque;
+
+use super::{AnyCancellable, Completion, Demand, Lock, Publish, Publisher, Shared, Subscriber, Threadsafe};
+use super::relay;
+
+impl<T, E> Subscriber<T, E> where T: Threadsafe, T: 'static, E: Clone, E: Threadsafe, E: 'static {
+    // values arriving while `max_publishers` inner publishers are active wait for one of them to finish
+    pub fn flat_map<F, U>(self: &Shared<Self>, max_publishers: Demand, f: F) -> Shared<Subscriber<U, E>> where F: Fn(&T) -> Shared<Publisher<U, E>>, F: Threadsafe, F: 'static, T: Clone, U: Threadsafe, U: 'static {
+        let (publisher, subscriber) = relay();
+        let flatten = Flatten::new(&publisher, FlattenStrategy::Limit(max_publishers), f);
+        subscriber.store(flatten.attach(self));
+        subscriber
+    }
+}
+
+impl<U, E> Subscriber<Shared<Publisher<U, E>>, E> where U: Threadsafe, U: 'static, E: Clone, E: Threadsafe, E: 'static {
+    pub fn switch_to_latest(self: &Shared<Self>) -> Shared<Subscriber<U, E>> {
+        let (publisher, subscriber) = relay();
+        let flatten = Flatten::new(&publisher, FlattenStrategy::Switch, Shared::clone);
+        subscriber.store(flatten.attach(self));
+        subscriber
+    }
+}
+
+enum FlattenStrategy {
+    Limit(Demand),
+    Switch,
+}
+
+type Transform<T, U, E> = Box<threadsafe_dyn!(Fn(&T) -> Shared<Publisher<U, E>>)>;
+
+struct Flatten<T, U, E> {
+    publisher: Shared<Publisher<U, E>>,
+    strategy: FlattenStrategy,
+    transform: Transform<T, U, E>,
+    state: Lock<FlattenState<T>>,
+}
+
+struct FlattenState<T> {
+    next_id: u64,
+    inners: Vec<(u64, Vec<AnyCancellable>)>,
+    pending: VecDeque<T>,
+    finished: bool,
+}
+
+impl<T, U, E> Flatten<T, U, E> where T: Clone, T: Threadsafe, T: 'static, U: Threadsafe, U: 'static, E: Clone, E: Threadsafe, E: 'static {
+    fn new<F>(publisher: &Shared<Publisher<U, E>>, strategy: FlattenStrategy, f: F) -> Shared<Self> where F: Fn(&T) -> Shared<Publisher<U, E>>, F: Threadsafe, F: 'static {
+        let flatten = Self {
+            publisher: Shared::clone(publisher),
+            strategy,
+            transform: Box::new(f),
+            state: Lock::new(FlattenState {
+                next_id: 0,
+                inners: vec![],
+                pending: VecDeque::new(),
+                finished: false,
+            }),
+        };
+        Shared::new(flatten)
+    }
+
+    fn attach(self: &Shared<Self>, upstream: &Shared<Subscriber<T, E>>) -> AnyCancellable {
+        let flatten = Shared::clone(self);
+        let value = upstream.sink(move |v| flatten.receive_value(v));
+        let flatten = Shared::clone(self);
+        let completion = upstream.sink_completion(move |completion| flatten.receive_completion(completion));
+        AnyCancellable::new(move || {
+            drop(value);
+            drop(completion);
+        })
+    }
+
+    fn receive_value(self: &Shared<Self>, v: &T) {
+        if self.publisher.is_completed() {
+            return;
+        }
+        let Ok(mut guard) = self.state.lock() else {
+            return
+        };
+        match self.strategy {
+            FlattenStrategy::Switch => {
+                // the previous inner publisher is superseded and its subscription disposed
+                let superseded = std::mem::take(&mut guard.inners);
+                drop(guard);
+                drop(superseded);
+                self.subscribe(v);
+            },
+            FlattenStrategy::Limit(max_publishers) => {
+                let active = guard.inners.len() as u64;
+                if max_publishers.consumed(active + 1).is_some() {
+                    drop(guard);
+                    self.subscribe(v);
+                } else {
+                    guard.pending.push_back(v.clone());
+                }
+            },
+        }
+    }
+
+    fn receive_completion(&self, completion: &Completion<E>) {
+        if let Completion::Finished = completion {
+            let Ok(mut guard) = self.state.lock() else {
+                return
+            };
+            guard.finished = true;
+            if !guard.is_done() {
+                return;
+            }
+        }
+        self.finish(completion);
+    }
+
+    fn subscribe(self: &Shared<Self>, v: &T) {
+        let inner_publisher = (self.transform)(v);
+        let id = match self.state.lock() {
+            Ok(mut guard) => guard.push_inner(),
+            Err(_) => return,
+        };
+        let inner = Subscriber::new();
+        let mut cancellables = vec![];
+        let publisher = Shared::clone(&self.publisher);
+        inner.sink(move |v| publisher.send_value(v))
+            .store_in(&mut cancellables);
+        // the inner subscriber keeps the operator alive after the upstream is gone
+        let flatten = Shared::clone(self);
+        inner.sink_completion(move |completion| flatten.receive_inner_completion(id, completion))
+            .store_in(&mut cancellables);
+        inner_publisher.receive_subscriber(&inner)
+            .store_in(&mut cancellables);
+        let rejected = self.state.lock()
+            .ok()
+            .and_then(|mut guard| guard.store_inner(id, cancellables));
+        drop(rejected);
+    }
+
+    fn receive_inner_completion(self: &Shared<Self>, id: u64, completion: &Completion<E>) {
+        if let Completion::Failure(_) = completion {
+            self.finish(completion);
+            return;
+        }
+        let Ok(mut guard) = self.state.lock() else {
+            return
+        };
+        let Some(inner) = guard.remove_inner(id) else {
+            return
+        };
+        let next = guard.pending.pop_front();
+        let done = guard.is_done();
+        drop(guard);
+        drop(inner);
+        if let Some(next) = next {
+            self.subscribe(&next);
+        } else if done {
+            self.finish(&Completion::Finished);
+        }
+    }
+
+    fn finish(&self, completion: &Completion<E>) {
+        let inners = self.state.lock()
+            .map(|mut guard| {
+                guard.pending.clear();
+                std::mem::take(&mut guard.inners)
+            })
+            .unwrap_or_default();
+        drop(inners);
+        self.publisher.send_completion(completion);
+    }
+}
+
+impl<T> FlattenState<T> {
+    fn push_inner(&mut self) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.inners.push((id, vec![]));
+        id
+    }
+
+    // hands the cancellables back when the inner publisher was superseded in the meantime
+    fn store_inner(&mut self, id: u64, cancellables: Vec<AnyCancellable>) -> Option<Vec<AnyCancellable>> {
+        match self.inners.iter_mut().find(|(v, _)| *v == id) {
+            Some((_, inner)) => {
+                *inner = cancellables;
+                None
+            },
+            None => Some(cancellables),
+        }
+    }
+
+    fn remove_inner(&mut self, id: u64) -> Option<Vec<AnyCancellable>> {
+        let index = self.inners.iter()
+            .position(|(v, _)| *v == id)?;
+        let (_, inner) = self.inners.remove(index);
+        Some(inner)
+    }
+
+    fn is_done(&self) -> bool {
+        self.finished && self.inners.is_empty() && self.pending.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+    use super::super::testing::*;
+
+    #[test]
+    #[allow(clippy::arc_with_non_send_sync)]
+    fn flat_map() {
+        let publisher: Shared<Publisher<u64>> = Publisher::new();
+        let inners = Shared::new(Lock::new(vec![]));
+        let r = Shared::clone(&inners);
+        let flattened = publisher.subscribe()
+            .flat_map(Demand::unlimited(), move |_| {
+                let inner: Shared<Publisher<u64>> = Publisher::new();
+                r.lock().unwrap().push(Shared::clone(&inner));
+                inner
+            });
+        let recorded = TestSubscriber::new(&flattened);
+        publisher.send_value(&1);
+        publisher.send_value(&2);
+        let (a, b) = {
+            let inners = inners.lock().unwrap();
+            (Shared::clone(&inners[0]), Shared::clone(&inners[1]))
+        };
+        a.send_value(&10);
+        b.send_value(&20);
+        a.send_value(&11);
+        assert_eq!(recorded.values(), vec![10, 20, 11]);
+        publisher.send_completion(&Completion::Finished);
+        a.send_completion(&Completion::Finished);
+        assert!(!flattened.is_completed());
+        b.send_completion(&Completion::Finished);
+        assert!(flattened.is_completed());
+    }
+
+    #[test]
+    fn flat_map_completed_inner() {
+        let publisher: Shared<Publisher<u64>> = Publisher::new();
+        let flattened = publisher.subscribe()
+            .flat_map(Demand::max(1), |v| {
+                let inner: Shared<Publisher<u64>> = Publisher::with_replay(1);
+                inner.send_value(&(v * 10));
+                inner.send_completion(&Completion::Finished);
+                inner
+            });
+        let recorded = TestSubscriber::new(&flattened);
+        publisher.send_value(&1);
+        publisher.send_value(&2);
+        publisher.send_completion(&Completion::Finished);
+        assert_eq!(recorded.values(), vec![10, 20]);
+        assert!(flattened.is_completed());
+    }
+
+    #[test]
+    #[allow(clippy::arc_with_non_send_sync)]
+    fn flat_map_max_publishers() {
+        let publisher: Shared<Publisher<u64>> = Publisher::new();
+        let inners = Shared::new(Lock::new(vec![]));
+        let r = Shared::clone(&inners);
+        let flattened = publisher.subscribe()
+            .flat_map(Demand::max(1), move |v| {
+                let inner: Shared<Publisher<i32>> = Publisher::new();
+                r.lock().unwrap().push((*v, Shared::clone(&inner)));
+                inner
+            });
+        let recorded = TestSubscriber::new(&flattened);
+        publisher.send_value(&1);
+        publisher.send_value(&2);
+        assert_eq!(inners.lock().unwrap().len(), 1);
+        let first = Shared::clone(&inners.lock().unwrap()[0].1);
+        first.send_value(&10);
+        first.send_completion(&Completion::Finished);
+        let (v, second) = inners.lock().unwrap()[1].clone();
+        assert_eq!(v, 2);
+        second.send_value(&20);
+        assert_eq!(recorded.values(), vec![10, 20]);
+    }
+
+    #[test]
+    fn switch_to_latest() {
+        let publisher: Shared<Publisher<Shared<Publisher<u64>>>> = Publisher::new();
+        let switched = publisher.subscribe().switch_to_latest();
+        let recorded = TestSubscriber::new(&switched);
+        let a: Shared<Publisher<u64>> = Publisher::new();
+        let b: Shared<Publisher<u64>> = Publisher::new();
+        publisher.send_value(&a);
+        a.send_value(&1);
+        publisher.send_value(&b);
+        a.send_value(&2);
+        b.send_value(&3);
+        assert_eq!(recorded.values(), vec![1, 3]);
+        // the superseded subscription is removed from its publisher
+        assert!(a.state.lock().unwrap().subscriptions.is_empty());
+        assert_eq!(b.state.lock().unwrap().subscriptions.len(), 1);
+    }
+
+    #[test]
+    fn inner_failure() {
+        let publisher: Shared<Publisher<Shared<Publisher<u64, String>>, String>> = Publisher::new();
+        let switched = publisher.subscribe().switch_to_latest();
+        let completion = Shared::new(Lock::new(None));
+        let r = Shared::clone(&completion);
+        let _completion = switched.sink_completion(move |v| *r.lock().unwrap() = Some(v.clone()));
+        let a: Shared<Publisher<u64, String>> = Publisher::new();
+        publisher.send_value(&a);
+        a.send_completion(&Completion::Failure("failed".into()));
+        assert_eq!(*completion.lock().unwrap(), Some(Completion::Failure("failed".to_string())));
+    }
+}
@@ -0,0 +1,24 @@
+// the operators reach the items of the flavour through here
+use super::*;
+
+mod filter;
+mod transform;
+mod combine;
+mod flatten;
+mod schedule;
+mod time;
+mod failure;
+pub use self::time::ThrottleEdge;
+mod share;
+pub use self::share::Multicast;
+mod debug;
+pub use self::debug::{Breakpoints, EventHandlers};
+
+// a publisher feeding a fresh subscriber, which is how every operator hands values downstream
+pub(crate) type Relay<T, E> = (Shared<Publisher<T, E>>, Shared<Subscriber<T, E>>);
+
+pub(crate) fn relay<T, E>() -> Relay<T, E> where T: Threadsafe, T: 'static, E: Clone, E: Threadsafe, E: 'static {
+    let publisher = Publisher::new();
+    let subscriber = publisher.subscribe();
+    (publisher, subscriber)
+}
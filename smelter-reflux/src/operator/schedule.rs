@@ -0,0 +1,95 @@
+use std::time::Duration;
+
+use super::{AnyCancellable, Lock, Publish, Publisher, Scheduler, Shared, Subscriber, Threadsafe};
+use super::relay;
+
+impl<T, E> Subscriber<T, E> where T: Threadsafe, T: 'static, E: Clone, E: Threadsafe, E: 'static {
+    // values and completion are delivered downstream as actions on the scheduler
+    pub fn receive_on<S>(self: &Shared<Self>, scheduler: &Shared<S>) -> Shared<Subscriber<T, E>> where S: Scheduler, S: Threadsafe, S: 'static, T: Clone {
+        let (publisher, subscriber) = relay();
+        let completion_publisher = Shared::clone(&publisher);
+        let completion_scheduler = Shared::clone(scheduler);
+        subscriber.store(self.sink_completion(move |completion| {
+            let publisher = Shared::clone(&completion_publisher);
+            let completion = completion.clone();
+            completion_scheduler.schedule(Box::new(move || publisher.send_completion(&completion)));
+        }));
+        let scheduler = Shared::clone(scheduler);
+        subscriber.store(self.sink(move |v| {
+            let publisher = Shared::clone(&publisher);
+            let v = v.clone();
+            scheduler.schedule(Box::new(move || publisher.send_value(&v)));
+        }));
+        subscriber
+    }
+
+    // attaches this subscriber to the publisher as an action on the scheduler,
+    // cancelling before the action runs prevents the subscription altogether
+    // the cancellable is only `Send` in the flavour whose `shared.rs` requires it, which is when it has to cross threads
+    #[allow(clippy::arc_with_non_send_sync)]
+    pub fn subscribe_on<S>(self: &Shared<Self>, publisher: &Shared<Publisher<T, E>>, scheduler: &Shared<S>) -> AnyCancellable where S: Scheduler {
+        let subscription: Shared<Lock<Option<AnyCancellable>>> = Shared::new(Lock::new(None));
+        let subscription_ref = Shared::downgrade(&subscription);
+        let subscriber = Shared::clone(self);
+        let publisher = Shared::clone(publisher);
+        let scheduled = scheduler.schedule_after(Duration::ZERO, Box::new(move || {
+            let cancellable = publisher.receive_subscriber(&subscriber);
+            if let Some(subscription) = subscription_ref.upgrade() {
+                if let Ok(mut guard) = subscription.lock() {
+                    *guard = Some(cancellable);
+                }
+            }
+        }));
+        AnyCancellable::new(move || {
+            drop(scheduled);
+            let cancellable = subscription.lock()
+                .ok()
+                .and_then(|mut guard| guard.take());
+            drop(cancellable);
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+    use super::super::testing::*;
+
+    #[test]
+    fn receive_on() {
+        let scheduler = VirtualTimeScheduler::new();
+        let publisher: Shared<Publisher<u64>> = Publisher::new();
+        let subscriber: Shared<Subscriber<u64>> = Subscriber::new();
+        let received = subscriber.receive_on(&scheduler);
+        let recorded = TestSubscriber::new(&received);
+        let _subscription = publisher.receive_subscriber(&subscriber);
+        publisher.send_value(&1);
+        publisher.send_value(&2);
+        publisher.send_completion(&Completion::Finished);
+        assert!(recorded.values().is_empty());
+        assert!(!received.is_completed());
+        scheduler.run();
+        assert_eq!(recorded.values(), vec![1, 2]);
+        assert!(received.is_completed());
+    }
+
+    #[test]
+    fn subscribe_on() {
+        let scheduler = VirtualTimeScheduler::new();
+        let publisher: Shared<Publisher<u64>> = Publisher::new();
+        let subscriber: Shared<Subscriber<u64>> = Subscriber::new();
+        let recorded = TestSubscriber::new(&subscriber);
+        let _subscription = subscriber.subscribe_on(&publisher, &scheduler);
+        publisher.send_value(&1);
+        scheduler.run();
+        publisher.send_value(&2);
+        assert_eq!(recorded.values(), vec![2]);
+
+        let cancelled: Shared<Subscriber<u64>> = Subscriber::new();
+        let cancelled_recorded = TestSubscriber::new(&cancelled);
+        drop(cancelled.subscribe_on(&publisher, &scheduler));
+        assert!(scheduler.is_idle());
+        publisher.send_value(&3);
+        assert!(cancelled_recorded.values().is_empty());
+    }
+}
@@ -0,0 +1,254 @@
+use std::convert::Infallible;
+
+use super::{AnyCancellable, Completion, Lock, Publish, Publisher, Shared, Subscriber, Threadsafe};
+
+impl<T, E> Subscriber<T, E> where T: Threadsafe, T: 'static, E: Clone, E: Threadsafe, E: 'static {
+    // fans this subscriber out to any number of downstream subscribers, forwarding
+    // only while at least one of them is attached
+    pub fn share(self: &Shared<Self>) -> Shared<Multicast<T, E>> {
+        Multicast::new(self, Publisher::new(), true)
+    }
+
+    // stays connected and replays up to `count` of the latest values to late subscribers
+    pub fn replay(self: &Shared<Self>, count: usize) -> Shared<Multicast<T, E>> where T: Clone {
+        let multicast = Multicast::new(self, Publisher::with_replay(count), false);
+        multicast.connect();
+        multicast
+    }
+}
+
+pub struct Multicast<T, E = Infallible> {
+    upstream: Shared<Subscriber<T, E>>,
+    publisher: Shared<Publisher<T, E>>,
+    state: Shared<Lock<MulticastState>>,
+}
+
+struct MulticastState {
+    ref_counted: bool,
+    subscribers: usize,
+    connected: bool,
+    // counts the connections, one made after disconnecting again is not kept
+    generation: u64,
+    connection: Vec<AnyCancellable>,
+}
+
+impl<T, E> Multicast<T, E> where T: Threadsafe, T: 'static, E: Clone, E: Threadsafe, E: 'static {
+    // the connection cancellables are only `Send` in the flavour whose `shared.rs` makes them so
+    #[allow(clippy::arc_with_non_send_sync)]
+    fn new(upstream: &Shared<Subscriber<T, E>>, publisher: Shared<Publisher<T, E>>, ref_counted: bool) -> Shared<Self> {
+        let multicast = Self {
+            upstream: Shared::clone(upstream),
+            publisher,
+            state: Shared::new(Lock::new(MulticastState {
+                ref_counted,
+                subscribers: 0,
+                connected: false,
+                generation: 0,
+                connection: vec![],
+            })),
+        };
+        Shared::new(multicast)
+    }
+
+    // a subscriber fed by this multicast, counting as a downstream for as long as it is alive
+    pub fn subscribe(&self) -> Shared<Subscriber<T, E>> {
+        let subscriber = Subscriber::new();
+        subscriber.store(self.receive_subscriber(&subscriber));
+        subscriber
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.state.lock()
+            .map(|guard| guard.connected)
+            .unwrap_or(false)
+    }
+
+    // decided under the lock so that concurrent downstreams connect only once, and connected
+    // without it so that what the upstream forwards right away is free to subscribe again
+    fn connect(&self) {
+        let generation = self.state.lock()
+            .ok()
+            .and_then(|mut guard| {
+                if std::mem::replace(&mut guard.connected, true) {
+                    return None;
+                }
+                guard.generation += 1;
+                Some(guard.generation)
+            });
+        let Some(generation) = generation else {
+            return
+        };
+        let publisher = Shared::clone(&self.publisher);
+        let connection = vec![
+            self.upstream.forward_completion(&self.publisher),
+            self.upstream.sink(move |v| publisher.send_value(v)),
+        ];
+        // a completed upstream takes no more sinks, the completion it recorded is forwarded instead
+        if let Some(completion) = self.upstream.completion() {
+            self.publisher.send_completion(&completion);
+        }
+        let disconnected = self.state.lock()
+            .map(|mut guard| {
+                if guard.connected && guard.generation == generation {
+                    guard.connection = connection;
+                    vec![]
+                } else {
+                    connection
+                }
+            })
+            .unwrap_or_default();
+        drop(disconnected);
+    }
+}
+
+impl<T, E> Publish for Multicast<T, E> where T: Threadsafe, T: 'static, E: Clone, E: Threadsafe, E: 'static {
+    type Output = T;
+    type Failure = E;
+
+    fn receive_subscriber(&self, subscriber: &Shared<Subscriber<T, E>>) -> AnyCancellable {
+        // the downstream is attached first so that nothing forwarded on connecting is missed
+        let subscription = self.publisher.receive_subscriber(subscriber);
+        if let Ok(mut guard) = self.state.lock() {
+            guard.subscribers += 1;
+        }
+        self.connect();
+        let state = Shared::clone(&self.state);
+        AnyCancellable::new(move || {
+            drop(subscription);
+            let connection = state.lock()
+                .ok()
+                .and_then(|mut guard| {
+                    guard.subscribers -= 1;
+                    (guard.ref_counted && guard.subscribers == 0).then(|| {
+                        guard.connected = false;
+                        std::mem::take(&mut guard.connection)
+                    })
+                });
+            // removes the forwarding sinks from the upstream
+            drop(connection);
+        })
+    }
+
+    fn send_value(&self, v: &T) {
+        self.publisher.send_value(v);
+    }
+
+    fn send_completion(&self, completion: &Completion<E>) {
+        self.publisher.send_completion(completion);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+    use super::super::testing::*;
+
+    #[test]
+    fn share() {
+        let publisher: Shared<Publisher<u64>> = Publisher::new();
+        let calls = Shared::new(Lock::new(0));
+        let r = Shared::clone(&calls);
+        let shared = publisher.subscribe()
+            .map(move |v| {
+                *r.lock().unwrap() += 1;
+                v * 10
+            })
+            .share();
+        let a = shared.subscribe();
+        let b = shared.subscribe();
+        let a_recorded = TestSubscriber::new(&a);
+        let b_recorded = TestSubscriber::new(&b);
+        publisher.send_value(&1);
+        publisher.send_value(&2);
+        assert_eq!(a_recorded.values(), vec![10, 20]);
+        assert_eq!(b_recorded.values(), vec![10, 20]);
+        assert_eq!(*calls.lock().unwrap(), 2);
+    }
+
+    #[test]
+    fn share_ref_count() {
+        let publisher: Shared<Publisher<u64>> = Publisher::new();
+        let subscriber: Shared<Subscriber<u64>> = Subscriber::new();
+        let _subscription = publisher.receive_subscriber(&subscriber);
+        let shared = subscriber.share();
+        assert!(!shared.is_connected());
+        let a: Shared<Subscriber<u64>> = Subscriber::new();
+        let b: Shared<Subscriber<u64>> = Subscriber::new();
+        let a_recorded = TestSubscriber::new(&a);
+        let a_subscription = shared.receive_subscriber(&a);
+        let b_subscription = shared.receive_subscriber(&b);
+        assert!(shared.is_connected());
+        publisher.send_value(&1);
+        drop(a_subscription);
+        assert!(shared.is_connected());
+        drop(b_subscription);
+        assert!(!shared.is_connected());
+        publisher.send_value(&2);
+        // reconnects on the next downstream
+        let _a_subscription = shared.receive_subscriber(&a);
+        publisher.send_value(&3);
+        assert_eq!(a_recorded.values(), vec![1, 3]);
+    }
+
+    #[test]
+    fn share_drop_subscriber() {
+        let publisher: Shared<Publisher<u64>> = Publisher::new();
+        let shared = publisher.subscribe().share();
+        let a = shared.subscribe();
+        assert!(shared.is_connected());
+        drop(a);
+        assert!(!shared.is_connected());
+    }
+
+    #[test]
+    fn replay() {
+        let publisher: Shared<Publisher<u64>> = Publisher::new();
+        let replayed = publisher.subscribe().replay(2);
+        for v in 1..=3 {
+            publisher.send_value(&v);
+        }
+        let late: Shared<Subscriber<u64>> = Subscriber::new();
+        let recorded = TestSubscriber::new(&late);
+        let _subscription = replayed.receive_subscriber(&late);
+        publisher.send_value(&4);
+        assert_eq!(recorded.values(), vec![2, 3, 4]);
+        publisher.send_completion(&Completion::Finished);
+        assert!(late.is_completed());
+    }
+
+    #[test]
+    #[allow(clippy::arc_with_non_send_sync)]
+    fn share_completed() {
+        let publisher: Shared<Publisher<u64>> = Publisher::new();
+        publisher.send_completion(&Completion::Finished);
+        let shared = publisher.subscribe().share();
+        let a: Shared<Subscriber<u64>> = Subscriber::new();
+        let b: Shared<Subscriber<u64>> = Subscriber::new();
+        let a_recorded = TestSubscriber::new(&a);
+        let b_recorded = TestSubscriber::new(&b);
+        // subscribing again from the completion does not wait on the connecting one
+        let b_subscription = Shared::new(Lock::new(None));
+        let r = Shared::clone(&b_subscription);
+        let shared_ref = Shared::clone(&shared);
+        let b_ref = Shared::clone(&b);
+        let _sink = a.sink_completion(move |_| {
+            *r.lock().unwrap() = Some(shared_ref.receive_subscriber(&b_ref));
+        });
+        let _a_subscription = shared.receive_subscriber(&a);
+        assert_eq!(a_recorded.completion(), Some(Completion::Finished));
+        assert_eq!(b_recorded.completion(), Some(Completion::Finished));
+    }
+
+    #[test]
+    fn replay_completed() {
+        let publisher: Shared<Publisher<u64, &str>> = Publisher::new();
+        let subscriber: Shared<Subscriber<u64, &str>> = Subscriber::new();
+        let _subscription = publisher.receive_subscriber(&subscriber);
+        publisher.send_completion(&Completion::Failure("failed"));
+        let replayed = subscriber.replay(2);
+        let late: Shared<Subscriber<u64, &str>> = Subscriber::new();
+        let recorded = TestSubscriber::new(&late);
+        let _late_subscription = replayed.receive_subscriber(&late);
+        assert_eq!(recorded.completion(), Some(Completion::Failure("failed")));
+    }
+}
@@ -0,0 +1,425 @@
+use std::{collections::VecDeque, time::Duration};
+
+use super::{AnyCancellable, Completion, Lock, Publish, Publisher, Scheduler, Shared, Subscriber, Threadsafe};
+use super::relay;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ThrottleEdge {
+    // emits the first value of each interval
+    Leading,
+    // emits the last value of each interval once it elapses
+    Trailing,
+}
+
+impl<T, E> Subscriber<T, E> where T: Threadsafe, T: 'static, E: Clone, E: Threadsafe, E: 'static {
+    // emits a value once no other value has arrived for `due`
+    pub fn debounce<S>(self: &Shared<Self>, due: Duration, scheduler: &Shared<S>) -> Shared<Subscriber<T, E>> where S: Scheduler, S: Threadsafe, S: 'static, T: Clone {
+        let (publisher, subscriber) = relay();
+        let debounce: Shared<Timed<Option<T>>> = Shared::new(Timed::new(None));
+        let completion_debounce = Shared::clone(&debounce);
+        let completion_publisher = Shared::clone(&publisher);
+        subscriber.store(self.sink_completion(move |completion| {
+            completion_debounce.timer.cancel();
+            // a pending value is flushed before finishing
+            if let (Some(v), Completion::Finished) = (completion_debounce.take(), completion) {
+                completion_publisher.send_value(&v);
+            }
+            completion_publisher.send_completion(completion);
+        }));
+        let scheduler = Shared::clone(scheduler);
+        subscriber.store(self.sink(move |v| {
+            debounce.replace(Some(v.clone()));
+            let debounce_ref = Shared::downgrade(&debounce);
+            let publisher_ref = Shared::downgrade(&publisher);
+            let handle = scheduler.schedule_after(due, Box::new(move || {
+                let Some(debounce) = debounce_ref.upgrade() else {
+                    return
+                };
+                if let (Some(v), Some(publisher)) = (debounce.take(), publisher_ref.upgrade()) {
+                    publisher.send_value(&v);
+                }
+            }));
+            debounce.timer.set(handle);
+        }));
+        subscriber
+    }
+
+    // emits at most one value per `interval`
+    pub fn throttle<S>(self: &Shared<Self>, interval: Duration, scheduler: &Shared<S>, edge: ThrottleEdge) -> Shared<Subscriber<T, E>> where S: Scheduler, S: Threadsafe, S: 'static, T: Clone {
+        let (publisher, subscriber) = relay();
+        let throttle = Shared::new(Timed::new(ThrottleState { open: false, pending: None }));
+        let completion_throttle = Shared::clone(&throttle);
+        let completion_publisher = Shared::clone(&publisher);
+        subscriber.store(self.sink_completion(move |completion| {
+            completion_throttle.timer.cancel();
+            let pending = completion_throttle.update(|state| state.pending.take()).flatten();
+            if let (Some(v), Completion::Finished) = (pending, completion) {
+                completion_publisher.send_value(&v);
+            }
+            completion_publisher.send_completion(completion);
+        }));
+        let scheduler = Shared::clone(scheduler);
+        subscriber.store(self.sink(move |v| {
+            let opened = throttle.update(|state| {
+                if edge == ThrottleEdge::Trailing {
+                    state.pending = Some(v.clone());
+                }
+                !std::mem::replace(&mut state.open, true)
+            });
+            if opened != Some(true) {
+                return;
+            }
+            if edge == ThrottleEdge::Leading {
+                publisher.send_value(v);
+            }
+            let throttle_ref = Shared::downgrade(&throttle);
+            let publisher_ref = Shared::downgrade(&publisher);
+            let handle = scheduler.schedule_after(interval, Box::new(move || {
+                let Some(throttle) = throttle_ref.upgrade() else {
+                    return
+                };
+                let pending = throttle.update(|state| {
+                    state.open = false;
+                    state.pending.take()
+                }).flatten();
+                if let (Some(v), Some(publisher)) = (pending, publisher_ref.upgrade()) {
+                    publisher.send_value(&v);
+                }
+            }));
+            throttle.timer.set(handle);
+        }));
+        subscriber
+    }
+
+    // shifts values and completion later by `due`, keeping their order
+    pub fn delay<S>(self: &Shared<Self>, due: Duration, scheduler: &Shared<S>) -> Shared<Subscriber<T, E>> where S: Scheduler, S: Threadsafe, S: 'static, T: Clone {
+        let (publisher, subscriber) = relay();
+        let delay = Shared::new(Delay {
+            publisher,
+            scheduler: Shared::clone(scheduler),
+            due,
+            timed: Timed::new(DelayState { scheduled: false, queue: VecDeque::new() }),
+        });
+        let completion_delay = Shared::clone(&delay);
+        subscriber.store(self.sink_completion(move |completion| {
+            completion_delay.push(Delayed::Completion(completion.clone()));
+        }));
+        subscriber.store(self.sink(move |v| {
+            delay.push(Delayed::Value(v.clone()));
+        }));
+        subscriber
+    }
+
+    // finishes when no value arrives within `due` of subscribing or of the previous value
+    pub fn timeout<S>(self: &Shared<Self>, due: Duration, scheduler: &Shared<S>) -> Shared<Subscriber<T, E>> where S: Scheduler, S: Threadsafe, S: 'static {
+        self.timeout_with(due, scheduler, || Completion::Finished)
+    }
+
+    // fails with the given error instead of finishing
+    pub fn timeout_with_error<S, F>(self: &Shared<Self>, due: Duration, scheduler: &Shared<S>, f: F) -> Shared<Subscriber<T, E>> where S: Scheduler, S: Threadsafe, S: 'static, F: Fn() -> E, F: Threadsafe, F: 'static {
+        self.timeout_with(due, scheduler, move || Completion::Failure(f()))
+    }
+
+    fn timeout_with<S, F>(self: &Shared<Self>, due: Duration, scheduler: &Shared<S>, f: F) -> Shared<Subscriber<T, E>> where S: Scheduler, S: Threadsafe, S: 'static, F: Fn() -> Completion<E>, F: Threadsafe, F: 'static {
+        let (publisher, subscriber) = relay();
+        let timeout = Shared::new(Timeout {
+            publisher: Shared::clone(&publisher),
+            scheduler: Shared::clone(scheduler),
+            due,
+            completion: Box::new(f),
+            timer: Timer::new(),
+        });
+        timeout.restart();
+        let subscription_timeout = Shared::clone(&timeout);
+        subscriber.store(self.sink_subscription(move || subscription_timeout.restart()));
+        let completion_timeout = Shared::clone(&timeout);
+        let completion_publisher = Shared::clone(&publisher);
+        subscriber.store(self.sink_completion(move |completion| {
+            completion_timeout.timer.cancel();
+            completion_publisher.send_completion(completion);
+        }));
+        subscriber.store(self.sink(move |v| {
+            if publisher.is_completed() {
+                return;
+            }
+            timeout.restart();
+            publisher.send_value(v);
+        }));
+        subscriber
+    }
+}
+
+// the pending scheduled action of an operator, replacing it cancels the previous one
+struct Timer {
+    handle: Lock<Option<AnyCancellable>>,
+}
+
+impl Timer {
+    fn new() -> Self {
+        Self {
+            handle: Lock::new(None),
+        }
+    }
+
+    fn set(&self, handle: AnyCancellable) {
+        let previous = self.handle.lock()
+            .ok()
+            .and_then(|mut guard| guard.replace(handle));
+        drop(previous);
+    }
+
+    fn cancel(&self) {
+        let previous = self.handle.lock()
+            .ok()
+            .and_then(|mut guard| guard.take());
+        drop(previous);
+    }
+}
+
+// operator state next to its timer
+struct Timed<V> {
+    state: Lock<V>,
+    timer: Timer,
+}
+
+impl<V> Timed<V> {
+    fn new(state: V) -> Self {
+        Self {
+            state: Lock::new(state),
+            timer: Timer::new(),
+        }
+    }
+
+    // the state lock is never held while values are sent or actions are scheduled
+    fn update<F, R>(&self, f: F) -> Option<R> where F: FnOnce(&mut V) -> R {
+        self.state.lock()
+            .ok()
+            .map(|mut guard| f(&mut guard))
+    }
+}
+
+impl<T> Timed<Option<T>> {
+    fn replace(&self, v: Option<T>) {
+        let previous = self.update(|state| std::mem::replace(state, v));
+        drop(previous);
+    }
+
+    fn take(&self) -> Option<T> {
+        self.update(|state| state.take()).flatten()
+    }
+}
+
+struct ThrottleState<T> {
+    open: bool,
+    pending: Option<T>,
+}
+
+enum Delayed<T, E> {
+    Value(T),
+    Completion(Completion<E>),
+}
+
+struct DelayState<T, E> {
+    scheduled: bool,
+    queue: VecDeque<(Duration, Delayed<T, E>)>,
+}
+
+struct Delay<T, E, S> {
+    publisher: Shared<Publisher<T, E>>,
+    scheduler: Shared<S>,
+    due: Duration,
+    timed: Timed<DelayState<T, E>>,
+}
+
+impl<T, E, S> Delay<T, E, S> where T: Threadsafe, T: 'static, E: Clone, E: Threadsafe, E: 'static, S: Scheduler, S: Threadsafe, S: 'static {
+    fn push(self: &Shared<Self>, delayed: Delayed<T, E>) {
+        let deadline = self.scheduler.now() + self.due;
+        let start = self.timed.update(|state| {
+            state.queue.push_back((deadline, delayed));
+            !std::mem::replace(&mut state.scheduled, true)
+        });
+        if start == Some(true) {
+            self.schedule(self.due);
+        }
+    }
+
+    // a single action is pending at a time, firing the front of the queue.
+    // it holds the delay strongly as queued values outlive the upstream completion
+    fn schedule(self: &Shared<Self>, delay: Duration) {
+        let delay_ref = Shared::clone(self);
+        let handle = self.scheduler.schedule_after(delay, Box::new(move || delay_ref.fire()));
+        self.timed.timer.set(handle);
+    }
+
+    fn fire(self: &Shared<Self>) {
+        let delayed = self.timed.update(|state| state.queue.pop_front()).flatten();
+        match delayed {
+            Some((_, Delayed::Value(v))) => self.publisher.send_value(&v),
+            Some((_, Delayed::Completion(completion))) => self.publisher.send_completion(&completion),
+            None => {},
+        }
+        let now = self.scheduler.now();
+        let next = self.timed.update(|state| {
+            let next = state.queue.front().map(|(deadline, _)| deadline.saturating_sub(now));
+            state.scheduled = next.is_some();
+            next
+        }).flatten();
+        if let Some(next) = next {
+            self.schedule(next);
+        }
+    }
+}
+
+type CompletionFactory<E> = Box<threadsafe_dyn!(Fn() -> Completion<E>)>;
+
+struct Timeout<T, E, S> {
+    publisher: Shared<Publisher<T, E>>,
+    scheduler: Shared<S>,
+    due: Duration,
+    completion: CompletionFactory<E>,
+    timer: Timer,
+}
+
+impl<T, E, S> Timeout<T, E, S> where T: Threadsafe, T: 'static, E: Clone, E: Threadsafe, E: 'static, S: Scheduler, S: Threadsafe, S: 'static {
+    fn restart(self: &Shared<Self>) {
+        let timeout_ref = Shared::downgrade(self);
+        let handle = self.scheduler.schedule_after(self.due, Box::new(move || {
+            let Some(timeout) = timeout_ref.upgrade() else {
+                return
+            };
+            timeout.publisher.send_completion(&(timeout.completion)());
+        }));
+        self.timer.set(handle);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::super::*;
+    use super::super::testing::*;
+
+    fn ms(v: u64) -> Duration {
+        Duration::from_millis(v)
+    }
+
+    #[test]
+    fn debounce() {
+        let scheduler = VirtualTimeScheduler::new();
+        let publisher: Shared<Publisher<&str>> = Publisher::new();
+        let subscriber: Shared<Subscriber<&str>> = Subscriber::new();
+        let recorded = TestSubscriber::new(&subscriber.debounce(ms(300), &scheduler));
+        let _subscription = publisher.receive_subscriber(&subscriber);
+        for v in ["s", "sm", "sme"] {
+            publisher.send_value(&v);
+            scheduler.advance_by(ms(100));
+        }
+        assert!(recorded.values().is_empty());
+        scheduler.advance_by(ms(200));
+        assert_eq!(recorded.values(), vec!["sme"]);
+        publisher.send_value(&"smelter");
+        publisher.send_completion(&Completion::Finished);
+        assert_eq!(recorded.values(), vec!["sme", "smelter"]);
+        assert!(scheduler.is_idle());
+    }
+
+    #[test]
+    fn throttle_leading() {
+        let scheduler = VirtualTimeScheduler::new();
+        let publisher: Shared<Publisher<u64>> = Publisher::new();
+        let subscriber: Shared<Subscriber<u64>> = Subscriber::new();
+        let recorded = TestSubscriber::new(&subscriber.throttle(ms(100), &scheduler, ThrottleEdge::Leading));
+        let _subscription = publisher.receive_subscriber(&subscriber);
+        for v in 0..10 {
+            publisher.send_value(&v);
+            scheduler.advance_by(ms(30));
+        }
+        assert_eq!(recorded.values(), vec![0, 4, 8]);
+    }
+
+    #[test]
+    fn throttle_trailing() {
+        let scheduler = VirtualTimeScheduler::new();
+        let publisher: Shared<Publisher<u64>> = Publisher::new();
+        let subscriber: Shared<Subscriber<u64>> = Subscriber::new();
+        let recorded = TestSubscriber::new(&subscriber.throttle(ms(100), &scheduler, ThrottleEdge::Trailing));
+        let _subscription = publisher.receive_subscriber(&subscriber);
+        for v in 0..10 {
+            publisher.send_value(&v);
+            scheduler.advance_by(ms(30));
+        }
+        assert_eq!(recorded.values(), vec![3, 7]);
+        scheduler.run();
+        assert_eq!(recorded.values(), vec![3, 7, 9]);
+    }
+
+    #[test]
+    fn delay() {
+        let scheduler = VirtualTimeScheduler::new();
+        let publisher: Shared<Publisher<u64>> = Publisher::new();
+        let subscriber: Shared<Subscriber<u64>> = Subscriber::new();
+        let delayed = subscriber.delay(ms(50), &scheduler);
+        let recorded = TestSubscriber::new(&delayed);
+        let _subscription = publisher.receive_subscriber(&subscriber);
+        publisher.send_value(&1);
+        scheduler.advance_by(ms(20));
+        publisher.send_value(&2);
+        publisher.send_completion(&Completion::Finished);
+        scheduler.advance_by(ms(30));
+        assert_eq!(recorded.values(), vec![1]);
+        assert!(!delayed.is_completed());
+        scheduler.advance_by(ms(20));
+        assert_eq!(recorded.values(), vec![1, 2]);
+        assert!(delayed.is_completed());
+    }
+
+    #[test]
+    fn queue_scheduler() {
+        // the queue scheduler runs delayed actions without waiting for them
+        let scheduler = QueueScheduler::new();
+        let publisher: Shared<Publisher<u64>> = Publisher::new();
+        let subscriber: Shared<Subscriber<u64>> = Subscriber::new();
+        let delayed = subscriber.debounce(ms(300), &scheduler)
+            .delay(ms(50), &scheduler);
+        let recorded = TestSubscriber::new(&delayed);
+        let _subscription = publisher.receive_subscriber(&subscriber);
+        publisher.send_value(&1);
+        publisher.send_value(&2);
+        publisher.send_completion(&Completion::Finished);
+        assert_eq!(recorded.values(), vec![1, 2]);
+        assert!(delayed.is_completed());
+    }
+
+    #[test]
+    fn timeout() {
+        let scheduler = VirtualTimeScheduler::new();
+        let publisher: Shared<Publisher<u64>> = Publisher::new();
+        let subscriber: Shared<Subscriber<u64>> = Subscriber::new();
+        let timed = subscriber.timeout(ms(100), &scheduler);
+        let recorded = TestSubscriber::new(&timed);
+        let _subscription = publisher.receive_subscriber(&subscriber);
+        scheduler.advance_by(ms(80));
+        publisher.send_value(&1);
+        scheduler.advance_by(ms(80));
+        assert!(!timed.is_completed());
+        scheduler.advance_by(ms(20));
+        assert!(timed.is_completed());
+        publisher.send_value(&2);
+        assert_eq!(recorded.values(), vec![1]);
+    }
+
+    #[test]
+    fn timeout_with_error() {
+        let scheduler = VirtualTimeScheduler::new();
+        let publisher: Shared<Publisher<u64, &str>> = Publisher::new();
+        let subscriber: Shared<Subscriber<u64, &str>> = Subscriber::new();
+        let completions = Shared::new(Lock::new(vec![]));
+        let r = Shared::clone(&completions);
+        let _sink = subscriber.timeout_with_error(ms(100), &scheduler, || "timed out")
+            .sink_completion(move |v| r.lock().unwrap().push(v.clone()));
+        let _subscription = publisher.receive_subscriber(&subscriber);
+        scheduler.run();
+        assert_eq!(*completions.lock().unwrap(), vec![Completion::Failure("timed out")]);
+    }
+}
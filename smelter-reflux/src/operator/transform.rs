@@ -0,0 +1,144 @@
+use super::{Completion, Lock, Publish, Shared, Subscriber, Threadsafe};
+use super::relay;
+
+impl<T, E> Subscriber<T, E> where T: Threadsafe, T: 'static, E: Clone, E: Threadsafe, E: 'static {
+    pub fn compact_map<F, S>(self: &Shared<Self>, f: F) -> Shared<Subscriber<S, E>> where F: Fn(&T) -> Option<S>, F: Threadsafe, F: 'static, S: Threadsafe, S: 'static {
+        let (publisher, subscriber) = relay();
+        subscriber.store(self.forward_completion(&publisher));
+        subscriber.store(self.sink(move |v| {
+            if let Some(v) = f(v) {
+                publisher.send_value(&v);
+            }
+        }));
+        subscriber
+    }
+
+    pub fn scan<F, S>(self: &Shared<Self>, initial: S, f: F) -> Shared<Subscriber<S, E>> where F: Fn(&S, &T) -> S, F: Threadsafe, F: 'static, S: Clone, S: Threadsafe, S: 'static {
+        let (publisher, subscriber) = relay();
+        subscriber.store(self.forward_completion(&publisher));
+        let accumulator = Lock::new(initial);
+        subscriber.store(self.sink(move |v| {
+            // sent without the lock, so that a sink sending back upstream does not deadlock
+            let accumulated = accumulator.lock()
+                .map(|mut guard| {
+                    *guard = f(&guard, v);
+                    guard.clone()
+                });
+            if let Ok(accumulated) = accumulated {
+                publisher.send_value(&accumulated);
+            }
+        }));
+        subscriber
+    }
+
+    pub fn reduce<F, S>(self: &Shared<Self>, initial: S, f: F) -> Shared<Subscriber<S, E>> where F: Fn(&S, &T) -> S, F: Threadsafe, F: 'static, S: Clone, S: Threadsafe, S: 'static {
+        let (publisher, subscriber) = relay();
+        let accumulator = Shared::new(Lock::new(initial));
+        let accumulator_ref = Shared::clone(&accumulator);
+        subscriber.store(self.sink(move |v| {
+            if let Ok(mut guard) = accumulator_ref.lock() {
+                *guard = f(&guard, v);
+            }
+        }));
+        subscriber.store(self.sink_completion(move |completion| {
+            if let Completion::Finished = completion {
+                let accumulated = accumulator.lock()
+                    .map(|guard| guard.clone());
+                if let Ok(accumulated) = accumulated {
+                    publisher.send_value(&accumulated);
+                }
+            }
+            publisher.send_completion(completion);
+        }));
+        subscriber
+    }
+
+    // the values are emitted whenever this subscriber is attached to a publisher
+    pub fn prepend<I>(self: &Shared<Self>, values: I) -> Shared<Subscriber<T, E>> where I: IntoIterator<Item = T> {
+        let (publisher, subscriber) = relay();
+        subscriber.store(self.forward_completion(&publisher));
+        let values: Vec<T> = values.into_iter().collect();
+        let prefix_publisher = Shared::clone(&publisher);
+        subscriber.store(self.sink_subscription(move || {
+            for v in values.iter() {
+                prefix_publisher.send_value(v);
+            }
+        }));
+        subscriber.store(self.sink(move |v| publisher.send_value(v)));
+        subscriber
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+    use super::super::testing::*;
+
+    #[test]
+    fn compact_map() {
+        let publisher: Shared<Publisher<&str>> = Publisher::new();
+        let subscriber: Shared<Subscriber<&str>> = Subscriber::new();
+        let recorded = TestSubscriber::new(&subscriber.compact_map(|v| v.parse::<u64>().ok()));
+        let _subscription = publisher.receive_subscriber(&subscriber);
+        for v in ["1", "a", "2", "", "3"] {
+            publisher.send_value(&v);
+        }
+        assert_eq!(recorded.values(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn scan() {
+        let publisher: Shared<Publisher<u64>> = Publisher::new();
+        let subscriber: Shared<Subscriber<u64>> = Subscriber::new();
+        let recorded = TestSubscriber::new(&subscriber.scan(0, |acc, v| acc + v));
+        let _subscription = publisher.receive_subscriber(&subscriber);
+        for v in 1..=4 {
+            publisher.send_value(&v);
+        }
+        assert_eq!(recorded.values(), vec![1, 3, 6, 10]);
+    }
+
+    #[test]
+    fn scan_reentrant() {
+        let publisher: Shared<Publisher<u64>> = Publisher::new();
+        let values = Shared::new(Lock::new(vec![]));
+        let r = Shared::clone(&values);
+        let source = Shared::clone(&publisher);
+        let _sink = publisher.subscribe()
+            .scan(0, |acc, v| acc + v)
+            .sink(move |v| {
+                r.lock().unwrap().push(*v);
+                if *v == 1 {
+                    source.send_value(&10);
+                }
+            });
+        publisher.send_value(&1);
+        assert_eq!(*values.lock().unwrap(), vec![1, 11]);
+    }
+
+    #[test]
+    fn reduce() {
+        let publisher: Shared<Publisher<u64>> = Publisher::new();
+        let subscriber: Shared<Subscriber<u64>> = Subscriber::new();
+        let reduced = subscriber.reduce(0, |acc, v| acc + v);
+        let recorded = TestSubscriber::new(&reduced);
+        let _subscription = publisher.receive_subscriber(&subscriber);
+        for v in 1..=4 {
+            publisher.send_value(&v);
+        }
+        assert!(recorded.values().is_empty());
+        publisher.send_completion(&Completion::Finished);
+        assert_eq!(recorded.values(), vec![10]);
+        assert!(reduced.is_completed());
+    }
+
+    #[test]
+    fn prepend() {
+        let publisher: Shared<Publisher<u64>> = Publisher::new();
+        let subscriber: Shared<Subscriber<u64>> = Subscriber::new();
+        let recorded = TestSubscriber::new(&subscriber.prepend([1, 2]).map(|v| v * 10));
+        let _subscription = publisher.receive_subscriber(&subscriber);
+        publisher.send_value(&3);
+        assert_eq!(recorded.values(), vec![10, 20, 30]);
+    }
+}
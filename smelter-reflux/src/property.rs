@@ -0,0 +1,236 @@
+use super::{AnyCancellable, Lock, Property, Shared, Subscriber, Threadsafe};
+
+// what derived properties read from, either a property or one derived from it
+pub trait ReadProperty<T> {
+    fn value(&self) -> T;
+    fn subscriber(&self) -> &Shared<Subscriber<T>>;
+}
+
+impl<T> ReadProperty<T> for Property<T> where T: Clone, T: Threadsafe, T: 'static {
+    fn value(&self) -> T {
+        Property::value(self)
+    }
+
+    fn subscriber(&self) -> &Shared<Subscriber<T>> {
+        Property::subscriber(self)
+    }
+}
+
+impl<T> Property<T> where T: Clone, T: Threadsafe, T: 'static {
+    pub fn map<U, F>(self: &Shared<Self>, f: F) -> Shared<ReadOnlyProperty<U>> where F: Fn(&T) -> U, F: Threadsafe, F: 'static, U: Clone, U: Threadsafe, U: 'static {
+        derive_map(self, f)
+    }
+
+    pub fn zip<U, P>(self: &Shared<Self>, other: &Shared<P>) -> Shared<ReadOnlyProperty<(T, U)>> where P: ReadProperty<U>, P: Threadsafe, P: 'static, U: Clone, U: Threadsafe, U: 'static {
+        derive_zip(self, other)
+    }
+}
+
+// follows the properties it was derived from, it cannot be accepted into
+pub struct ReadOnlyProperty<T> where T: Clone, T: Threadsafe, T: 'static {
+    property: Shared<Property<T>>,
+    // the sinks on the sources, removed once this property is dropped
+    _binding: AnyCancellable,
+}
+
+impl<T> ReadOnlyProperty<T> where T: Clone, T: Threadsafe, T: 'static {
+    pub fn value(&self) -> T {
+        self.property.value()
+    }
+
+    pub fn subscriber(&self) -> &Shared<Subscriber<T>> {
+        self.property.subscriber()
+    }
+
+    pub fn map<U, F>(self: &Shared<Self>, f: F) -> Shared<ReadOnlyProperty<U>> where F: Fn(&T) -> U, F: Threadsafe, F: 'static, U: Clone, U: Threadsafe, U: 'static {
+        derive_map(self, f)
+    }
+
+    pub fn zip<U, P>(self: &Shared<Self>, other: &Shared<P>) -> Shared<ReadOnlyProperty<(T, U)>> where P: ReadProperty<U>, P: Threadsafe, P: 'static, U: Clone, U: Threadsafe, U: 'static {
+        derive_zip(self, other)
+    }
+}
+
+impl<T> ReadProperty<T> for ReadOnlyProperty<T> where T: Clone, T: Threadsafe, T: 'static {
+    fn value(&self) -> T {
+        ReadOnlyProperty::value(self)
+    }
+
+    fn subscriber(&self) -> &Shared<Subscriber<T>> {
+        ReadOnlyProperty::subscriber(self)
+    }
+}
+
+fn derive_map<T, U, P, F>(source: &Shared<P>, f: F) -> Shared<ReadOnlyProperty<U>> where P: ReadProperty<T>, P: Threadsafe, P: 'static, F: Fn(&T) -> U, F: Threadsafe, F: 'static, T: Threadsafe, T: 'static, U: Clone, U: Threadsafe, U: 'static {
+    let property = Property::new(f(&source.value()));
+    let property_ref = Shared::clone(&property);
+    let binding = source.subscriber()
+        .sink(move |v| property_ref.accept(&f(v)));
+    // a derived source is kept alive for as long as something is derived from it
+    let source = Shared::clone(source);
+    let derived = ReadOnlyProperty {
+        property,
+        _binding: AnyCancellable::new(move || {
+            drop(binding);
+            drop(source);
+        }),
+    };
+    Shared::new(derived)
+}
+
+fn derive_zip<T, U, P, Q>(a: &Shared<P>, b: &Shared<Q>) -> Shared<ReadOnlyProperty<(T, U)>> where P: ReadProperty<T>, P: Threadsafe, P: 'static, Q: ReadProperty<U>, Q: Threadsafe, Q: 'static, T: Clone, T: Threadsafe, T: 'static, U: Clone, U: Threadsafe, U: 'static {
+    let property = Property::new((a.value(), b.value()));
+    // the other side is read on every change, its own sink has already stored the new value
+    let property_ref = Shared::clone(&property);
+    let b_ref = Shared::downgrade(b);
+    let a_binding = a.subscriber().sink(move |v| {
+        if let Some(u) = b_ref.upgrade().map(|b| b.value()) {
+            property_ref.accept(&(v.clone(), u));
+        }
+    });
+    let property_ref = Shared::clone(&property);
+    let a_ref = Shared::downgrade(a);
+    let b_binding = b.subscriber().sink(move |u| {
+        if let Some(v) = a_ref.upgrade().map(|a| a.value()) {
+            property_ref.accept(&(v, u.clone()));
+        }
+    });
+    let (a, b) = (Shared::clone(a), Shared::clone(b));
+    let derived = ReadOnlyProperty {
+        property,
+        _binding: AnyCancellable::new(move || {
+            drop(a_binding);
+            drop(b_binding);
+            drop((a, b));
+        }),
+    };
+    Shared::new(derived)
+}
+
+// keeps both properties in sync, starting from the value of `a`; the change each side
+// makes to the other is not echoed back, so converting back and forth never loops
+pub fn bind_bidirectional<T, U, F, G>(a: &Shared<Property<T>>, b: &Shared<Property<U>>, to: F, from: G) -> AnyCancellable where F: Fn(&T) -> U, F: Threadsafe, F: 'static, G: Fn(&U) -> T, G: Threadsafe, G: 'static, T: Clone, T: Threadsafe, T: 'static, U: Clone, U: Threadsafe, U: 'static {
+    b.accept(&to(&a.value()));
+    // the number of values each side has yet to receive from the other,
+    // counted rather than flagged since a queued accept is delivered later
+    let echoes: Shared<Lock<(usize, usize)>> = Shared::new(Lock::new((0, 0)));
+    let echoes_ref = Shared::clone(&echoes);
+    let b_ref = Shared::downgrade(b);
+    let a_binding = a.subscriber().sink(move |v| {
+        if !take_echo(&echoes_ref, |echoes| &mut echoes.0) {
+            return;
+        }
+        if let Some(b) = b_ref.upgrade() {
+            count_echo(&echoes_ref, |echoes| &mut echoes.1);
+            b.accept(&to(v));
+        }
+    });
+    let echoes_ref = Shared::clone(&echoes);
+    let a_ref = Shared::downgrade(a);
+    let b_binding = b.subscriber().sink(move |u| {
+        if !take_echo(&echoes_ref, |echoes| &mut echoes.1) {
+            return;
+        }
+        if let Some(a) = a_ref.upgrade() {
+            count_echo(&echoes_ref, |echoes| &mut echoes.0);
+            a.accept(&from(u));
+        }
+    });
+    AnyCancellable::new(move || {
+        drop(a_binding);
+        drop(b_binding);
+    })
+}
+
+// true when the value came from outside the binding
+fn take_echo<F>(echoes: &Shared<Lock<(usize, usize)>>, side: F) -> bool where F: Fn(&mut (usize, usize)) -> &mut usize {
+    echoes.lock()
+        .map(|mut guard| {
+            let count = side(&mut guard);
+            if *count == 0 {
+                return true;
+            }
+            *count -= 1;
+            false
+        })
+        .unwrap_or(false)
+}
+
+fn count_echo<F>(echoes: &Shared<Lock<(usize, usize)>>, side: F) where F: Fn(&mut (usize, usize)) -> &mut usize {
+    if let Ok(mut guard) = echoes.lock() {
+        *side(&mut guard) += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+    use super::super::testing::*;
+
+    #[test]
+    fn map() {
+        let count = Property::new(1u64);
+        let label = count.map(|v| v * 2)
+            .map(|v| format!("{} items", v));
+        assert_eq!(label.value(), "2 items");
+        let recorded = TestSubscriber::new(label.subscriber());
+        count.accept(&3);
+        assert_eq!(label.value(), "6 items");
+        assert_eq!(recorded.values(), vec!["6 items".to_string()]);
+    }
+
+    #[test]
+    fn map_dropped() {
+        let count = Property::new(1u64);
+        let doubled = count.map(|v| v * 2);
+        assert_eq!(count.subscriber().state.lock().unwrap().value_sinks().len(), 1);
+        drop(doubled);
+        assert!(count.subscriber().state.lock().unwrap().value_sinks().is_empty());
+    }
+
+    #[test]
+    fn zip() {
+        let width = Property::new(2u64);
+        let height = Property::new(3u64);
+        let depth = Property::new(4u64);
+        let volume = width.zip(&height)
+            .zip(&depth)
+            .map(|((w, h), d)| w * h * d);
+        assert_eq!(volume.value(), 24);
+        width.accept(&5);
+        depth.accept(&1);
+        assert_eq!(volume.value(), 15);
+    }
+
+    #[test]
+    fn bind_bidirectional() {
+        let celsius = Property::new(100i64);
+        let fahrenheit = Property::new(0i64);
+        let recorded = TestSubscriber::new(fahrenheit.subscriber());
+        let _binding = super::bind_bidirectional(&celsius, &fahrenheit, |c| c * 9 / 5 + 32, |f| (f - 32) * 5 / 9);
+        assert_eq!(fahrenheit.value(), 212);
+        celsius.accept(&0);
+        assert_eq!(fahrenheit.value(), 32);
+        fahrenheit.accept(&50);
+        assert_eq!(celsius.value(), 10);
+        // every change crosses the binding once, without being echoed back
+        assert_eq!(recorded.values(), vec![212, 32, 50]);
+    }
+
+    #[test]
+    fn bind_bidirectional_from_sink() {
+        let a = Property::new(0u64);
+        let b = Property::new(0u64);
+        let _binding = super::bind_bidirectional(&a, &b, |v| v + 1, |v| v - 1);
+        // an accept from inside a sink of `b` is queued, the binding still does not echo it
+        let b_ref = Shared::downgrade(&b);
+        let _sink = b.subscriber().sink(move |v| {
+            if *v == 11 {
+                b_ref.upgrade().unwrap().accept(&21);
+            }
+        });
+        a.accept(&10);
+        assert_eq!(b.value(), 21);
+        assert_eq!(a.value(), 20);
+    }
+}
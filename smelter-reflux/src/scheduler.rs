@@ -0,0 +1,346 @@
+use std::{collections::VecDeque, time::{Duration, Instant}};
+
+use super::{AnyCancellable, Lock, Shared, WeakShared};
+
+pub type Action = Box<threadsafe_dyn!(send FnOnce())>;
+
+pub trait Scheduler {
+    // time elapsed since the scheduler was created
+    fn now(&self) -> Duration;
+    fn schedule(&self, action: Action);
+    fn schedule_after(&self, delay: Duration, action: Action) -> AnyCancellable;
+}
+
+// runs every action synchronously on the calling thread, delays are ignored
+pub struct ImmediateScheduler {
+    start: Instant,
+}
+
+impl ImmediateScheduler {
+    pub fn new() -> Shared<Self> {
+        let scheduler = Self {
+            start: Instant::now(),
+        };
+        Shared::new(scheduler)
+    }
+}
+
+impl Scheduler for ImmediateScheduler {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    fn schedule(&self, action: Action) {
+        action();
+    }
+
+    fn schedule_after(&self, _delay: Duration, action: Action) -> AnyCancellable {
+        action();
+        AnyCancellable::new(|| {})
+    }
+}
+
+// trampoline that runs actions in order once the outermost action returns,
+// delayed actions are ordered by their due time without waiting for it
+pub struct QueueScheduler {
+    start: Instant,
+    state: Lock<QueueState>,
+    scheduler: WeakShared<QueueScheduler>,
+}
+
+struct QueueState {
+    draining: bool,
+    next_id: u64,
+    actions: VecDeque<ScheduledAction>,
+}
+
+impl QueueScheduler {
+    pub fn new() -> Shared<Self> {
+        Shared::new_cyclic(|scheduler| Self {
+            start: Instant::now(),
+            state: Lock::new(QueueState {
+                draining: false,
+                next_id: 0,
+                actions: VecDeque::new(),
+            }),
+            scheduler: WeakShared::clone(scheduler),
+        })
+    }
+
+    fn enqueue(&self, due: Duration, action: Action) -> Option<u64> {
+        let mut guard = self.state.lock().ok()?;
+        let id = guard.next_id;
+        guard.next_id += 1;
+        let index = guard.actions.iter()
+            .position(|v| v.due > due)
+            .unwrap_or(guard.actions.len());
+        guard.actions.insert(index, ScheduledAction { id, due, action });
+        Some(id)
+    }
+
+    fn drain(&self) {
+        let draining = self.state.lock()
+            .map(|mut guard| std::mem::replace(&mut guard.draining, true))
+            .unwrap_or(true);
+        if draining {
+            return;
+        }
+        loop {
+            let action = self.state.lock()
+                .ok()
+                .and_then(|mut guard| {
+                    let action = guard.actions.pop_front();
+                    guard.draining = action.is_some();
+                    action
+                });
+            let Some(action) = action else {
+                break
+            };
+            (action.action)();
+        }
+    }
+
+    fn cancel(&self, id: u64) {
+        let action = self.state.lock()
+            .ok()
+            .and_then(|mut guard| {
+                let index = guard.actions.iter().position(|v| v.id == id)?;
+                guard.actions.remove(index)
+            });
+        drop(action);
+    }
+}
+
+impl Scheduler for QueueScheduler {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    fn schedule(&self, action: Action) {
+        self.enqueue(self.now(), action);
+        self.drain();
+    }
+
+    // an action scheduled from inside another one can be cancelled until the outer one returns
+    fn schedule_after(&self, delay: Duration, action: Action) -> AnyCancellable {
+        let id = self.enqueue(self.now() + delay, action);
+        let scheduler = WeakShared::clone(&self.scheduler);
+        self.drain();
+        AnyCancellable::new(move || {
+            if let (Some(scheduler), Some(id)) = (scheduler.upgrade(), id) {
+                scheduler.cancel(id);
+            }
+        })
+    }
+}
+
+// a deterministic clock that only moves when advanced, for testing time based operators
+pub struct VirtualTimeScheduler {
+    state: Lock<VirtualTimeState>,
+    scheduler: WeakShared<VirtualTimeScheduler>,
+}
+
+struct VirtualTimeState {
+    now: Duration,
+    next_id: u64,
+    actions: Vec<ScheduledAction>,
+}
+
+impl VirtualTimeScheduler {
+    pub fn new() -> Shared<Self> {
+        Shared::new_cyclic(|scheduler| Self {
+            state: Lock::new(VirtualTimeState {
+                now: Duration::ZERO,
+                next_id: 0,
+                actions: vec![],
+            }),
+            scheduler: WeakShared::clone(scheduler),
+        })
+    }
+
+    pub fn advance_by(&self, duration: Duration) {
+        let target = self.now() + duration;
+        self.advance_to(target);
+    }
+
+    // runs every action due up to `target` in order, including ones scheduled along the way
+    pub fn advance_to(&self, target: Duration) {
+        loop {
+            let action = self.state.lock()
+                .ok()
+                .and_then(|mut guard| guard.next_due(target));
+            let Some(action) = action else {
+                break
+            };
+            (action.action)();
+        }
+        if let Ok(mut guard) = self.state.lock() {
+            guard.now = guard.now.max(target);
+        }
+    }
+
+    // runs until no action is left
+    pub fn run(&self) {
+        loop {
+            let due = self.state.lock()
+                .ok()
+                .and_then(|guard| guard.actions.first().map(|v| v.due));
+            let Some(due) = due else {
+                break
+            };
+            self.advance_to(due);
+        }
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.state.lock()
+            .map(|guard| guard.actions.is_empty())
+            .unwrap_or(true)
+    }
+
+    fn enqueue(&self, delay: Duration, action: Action) -> Option<u64> {
+        let mut guard = self.state.lock().ok()?;
+        let id = guard.next_id;
+        guard.next_id += 1;
+        let due = guard.now + delay;
+        let index = guard.actions.iter()
+            .position(|v| v.due > due)
+            .unwrap_or(guard.actions.len());
+        guard.actions.insert(index, ScheduledAction { id, due, action });
+        Some(id)
+    }
+
+    fn cancel(&self, id: u64) {
+        let action = self.state.lock()
+            .ok()
+            .and_then(|mut guard| {
+                let index = guard.actions.iter().position(|v| v.id == id)?;
+                Some(guard.actions.remove(index))
+            });
+        drop(action);
+    }
+}
+
+impl Scheduler for VirtualTimeScheduler {
+    fn now(&self) -> Duration {
+        self.state.lock()
+            .map(|guard| guard.now)
+            .unwrap_or_default()
+    }
+
+    fn schedule(&self, action: Action) {
+        self.enqueue(Duration::ZERO, action);
+    }
+
+    fn schedule_after(&self, delay: Duration, action: Action) -> AnyCancellable {
+        let id = self.enqueue(delay, action);
+        let scheduler = WeakShared::clone(&self.scheduler);
+        AnyCancellable::new(move || {
+            if let (Some(scheduler), Some(id)) = (scheduler.upgrade(), id) {
+                scheduler.cancel(id);
+            }
+        })
+    }
+}
+
+impl VirtualTimeState {
+    fn next_due(&mut self, target: Duration) -> Option<ScheduledAction> {
+        if self.actions.first()?.due > target {
+            return None;
+        }
+        let action = self.actions.remove(0);
+        self.now = self.now.max(action.due);
+        Some(action)
+    }
+}
+
+struct ScheduledAction {
+    id: u64,
+    due: Duration,
+    action: Action,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::super::*;
+
+    fn push(values: &Shared<Lock<Vec<u64>>>, v: u64) -> Action {
+        let values = Shared::clone(values);
+        Box::new(move || values.lock().unwrap().push(v))
+    }
+
+    #[test]
+    fn immediate() {
+        let scheduler = ImmediateScheduler::new();
+        let values = Shared::new(Lock::new(vec![]));
+        scheduler.schedule(push(&values, 1));
+        let _cancellable = scheduler.schedule_after(Duration::ZERO, push(&values, 2));
+        assert_eq!(*values.lock().unwrap(), vec![1, 2]);
+    }
+
+    #[test]
+    fn immediate_delay() {
+        let scheduler = ImmediateScheduler::new();
+        let values = Shared::new(Lock::new(vec![]));
+        let _cancellable = scheduler.schedule_after(Duration::from_secs(1), push(&values, 1));
+        assert_eq!(*values.lock().unwrap(), vec![1]);
+    }
+
+    #[test]
+    fn queue_trampoline() {
+        let scheduler = QueueScheduler::new();
+        let values = Shared::new(Lock::new(vec![]));
+        let inner_scheduler = Shared::clone(&scheduler);
+        let inner_values = Shared::clone(&values);
+        scheduler.schedule(Box::new(move || {
+            // nested actions run after the current one returns
+            inner_scheduler.schedule(push(&inner_values, 2));
+            inner_values.lock().unwrap().push(1);
+        }));
+        scheduler.schedule(push(&values, 3));
+        assert_eq!(*values.lock().unwrap(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn virtual_time() {
+        let scheduler = VirtualTimeScheduler::new();
+        let values = Shared::new(Lock::new(vec![]));
+        let _a = scheduler.schedule_after(Duration::from_millis(30), push(&values, 3));
+        let _b = scheduler.schedule_after(Duration::from_millis(10), push(&values, 1));
+        let c = scheduler.schedule_after(Duration::from_millis(20), push(&values, 2));
+        scheduler.schedule(push(&values, 0));
+        assert!(values.lock().unwrap().is_empty());
+        scheduler.advance_by(Duration::from_millis(10));
+        assert_eq!(*values.lock().unwrap(), vec![0, 1]);
+        assert_eq!(scheduler.now(), Duration::from_millis(10));
+        drop(c);
+        scheduler.advance_by(Duration::from_millis(25));
+        assert_eq!(*values.lock().unwrap(), vec![0, 1, 3]);
+        assert_eq!(scheduler.now(), Duration::from_millis(35));
+        assert!(scheduler.is_idle());
+    }
+
+    #[test]
+    #[allow(clippy::arc_with_non_send_sync)]
+    fn virtual_time_nested() {
+        let scheduler = VirtualTimeScheduler::new();
+        let times = Shared::new(Lock::new(vec![]));
+        let handles = Shared::new(Lock::new(vec![]));
+        let inner_handles = Shared::clone(&handles);
+        let inner_scheduler = Shared::clone(&scheduler);
+        let inner_times = Shared::clone(&times);
+        let _cancellable = scheduler.schedule_after(Duration::from_millis(10), Box::new(move || {
+            inner_times.lock().unwrap().push(inner_scheduler.now());
+            let nested_scheduler = Shared::clone(&inner_scheduler);
+            let nested_times = Shared::clone(&inner_times);
+            let handle = inner_scheduler.schedule_after(Duration::from_millis(5), Box::new(move || {
+                nested_times.lock().unwrap().push(nested_scheduler.now());
+            }));
+            inner_handles.lock().unwrap().push(handle);
+        }));
+        scheduler.run();
+        assert_eq!(*times.lock().unwrap(), vec![Duration::from_millis(10), Duration::from_millis(15)]);
+    }
+}
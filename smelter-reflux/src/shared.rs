@@ -0,0 +1,19 @@
+pub use std::sync::{Arc as Shared, Weak as WeakShared};
+
+pub(crate) use std::sync::Mutex as Lock;
+
+// a poisoned lock still holds the last value that was stored whole
+pub(crate) fn read<T>(lock: &Lock<T>) -> T where T: Clone {
+    lock.lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .clone()
+}
+
+pub trait Threadsafe {}
+
+impl<T> Threadsafe for T where T: ?Sized {}
+
+macro_rules! threadsafe_dyn {
+    (send $($bounds:tt)*) => { dyn $($bounds)* };
+    ($($bounds:tt)*) => { dyn $($bounds)* };
+}
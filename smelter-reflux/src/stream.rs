@@ -0,0 +1,245 @@
+use std::{collections::VecDeque, future::Future, pin::Pin, task::{Context, Poll, Waker}};
+
+use futures::{Stream, StreamExt};
+
+use super::{AnyCancellable, BufferingPolicy, Completion, Demand, Lock, Publish, Publisher, Shared, Subscriber, Threadsafe};
+
+impl<T, E> Subscriber<T, E> where T: Clone, T: Threadsafe, T: 'static, E: Clone, E: Threadsafe, E: 'static {
+    // the values this subscriber receives from now on, ending with its completion.
+    // each poll waiting for a value requests one more on top of the demand of the subscriber
+    pub fn values(self: &Shared<Self>) -> Values<T, E> {
+        let state: Shared<Lock<ValuesState<T, E>>> = Shared::new(Lock::new(ValuesState {
+            buffer: VecDeque::new(),
+            completion: None,
+            // a subscriber that completed earlier never delivers its completion
+            finished: self.is_completed(),
+            requested: false,
+            waker: None,
+        }));
+        let state_ref = Shared::clone(&state);
+        let value = self.sink(move |v| {
+            let waker = state_ref.lock()
+                .ok()
+                .and_then(|mut guard| {
+                    guard.buffer.push_back(v.clone());
+                    guard.requested = false;
+                    guard.waker.take()
+                });
+            if let Some(waker) = waker {
+                waker.wake();
+            }
+        });
+        let state_ref = Shared::clone(&state);
+        let completion = self.sink_completion(move |completion| {
+            let waker = state_ref.lock()
+                .ok()
+                .and_then(|mut guard| {
+                    guard.completion = Some(completion.clone());
+                    guard.finished = true;
+                    guard.waker.take()
+                });
+            if let Some(waker) = waker {
+                waker.wake();
+            }
+        });
+        Values {
+            state,
+            subscriber: Shared::clone(self),
+            cancellables: vec![value, completion],
+        }
+    }
+}
+
+impl<T, E> Publisher<T, E> where T: Clone, T: Threadsafe, T: 'static, E: Clone, E: Threadsafe, E: 'static {
+    // values sent before the stream asks for them are left to the buffering policy of the publisher
+    pub fn values(self: &Shared<Self>) -> Values<T, E> {
+        let subscriber = Subscriber::with_demand(Demand::nothing());
+        let mut values = subscriber.values();
+        values.cancellables.push(self.receive_subscriber(&subscriber));
+        values
+    }
+
+    // the returned task sends the output of the future once it is polled to completion,
+    // subscribers attached before spawning it receive the value
+    pub fn from_future<F>(future: F) -> (Shared<Self>, impl Future<Output = ()>) where F: Future<Output = Result<T, E>> {
+        // the single value waits for subscribers that have not asked for it yet
+        let publisher: Shared<Self> = Publisher::with_policy(BufferingPolicy::DropNewest(1));
+        let task_publisher = Shared::clone(&publisher);
+        let task = async move {
+            match future.await {
+                Ok(v) => {
+                    task_publisher.send_value(&v);
+                    task_publisher.send_completion(&Completion::Finished);
+                },
+                Err(e) => task_publisher.send_completion(&Completion::Failure(e)),
+            }
+        };
+        (publisher, task)
+    }
+
+    // the returned task forwards the items of the stream, stopping at the first error
+    pub fn from_stream<S>(stream: S) -> (Shared<Self>, impl Future<Output = ()>) where S: Stream<Item = Result<T, E>> {
+        let publisher: Shared<Self> = Publisher::new();
+        let task_publisher = Shared::clone(&publisher);
+        let task = async move {
+            let mut stream = std::pin::pin!(stream);
+            while let Some(item) = stream.next().await {
+                match item {
+                    Ok(v) => task_publisher.send_value(&v),
+                    Err(e) => {
+                        task_publisher.send_completion(&Completion::Failure(e));
+                        return;
+                    },
+                }
+            }
+            task_publisher.send_completion(&Completion::Finished);
+        };
+        (publisher, task)
+    }
+}
+
+// a stream over the values received by a subscriber, dropping it cancels the subscription
+pub struct Values<T, E> {
+    state: Shared<Lock<ValuesState<T, E>>>,
+    subscriber: Shared<Subscriber<T, E>>,
+    cancellables: Vec<AnyCancellable>,
+}
+
+struct ValuesState<T, E> {
+    buffer: VecDeque<T>,
+    completion: Option<Completion<E>>,
+    finished: bool,
+    // a value was requested and has not arrived yet
+    requested: bool,
+    waker: Option<Waker>,
+}
+
+impl<T, E> Values<T, E> where E: Clone {
+    // how the values ended, known once the stream has yielded its last value
+    pub fn completion(&self) -> Option<Completion<E>> {
+        self.state.lock()
+            .ok()
+            .and_then(|guard| guard.completion.clone())
+    }
+}
+
+impl<T, E> Stream for Values<T, E> where E: Clone {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let Ok(mut guard) = self.state.lock() else {
+            return Poll::Ready(None)
+        };
+        if let Some(v) = guard.buffer.pop_front() {
+            return Poll::Ready(Some(v));
+        }
+        if guard.finished {
+            return Poll::Ready(None);
+        }
+        guard.waker = Some(cx.waker().clone());
+        let request = !std::mem::replace(&mut guard.requested, true);
+        // a value delivered right away wakes the task through the sink
+        drop(guard);
+        if request {
+            self.subscriber.request(Demand::max(1));
+        }
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{channel::oneshot, executor::{block_on, LocalPool}, stream, task::LocalSpawnExt, StreamExt};
+
+    use super::super::*;
+
+    #[test]
+    fn values() {
+        let publisher: Shared<Publisher<u64>> = Publisher::with_policy(BufferingPolicy::DropNewest(10));
+        let values = publisher.values();
+        publisher.send_value(&1);
+        publisher.send_value(&2);
+        publisher.send_completion(&Completion::Finished);
+        assert_eq!(block_on(values.collect::<Vec<_>>()), vec![1, 2]);
+    }
+
+    #[test]
+    fn values_demand() {
+        // nothing is requested before polling, so only what the publisher buffers is left
+        let publisher: Shared<Publisher<u64>> = Publisher::with_policy(BufferingPolicy::DropNewest(1));
+        let mut values = publisher.values();
+        publisher.send_value(&1);
+        publisher.send_value(&2);
+        assert_eq!(block_on(values.next()), Some(1));
+        publisher.send_value(&3);
+        publisher.send_value(&4);
+        publisher.send_completion(&Completion::Finished);
+        assert_eq!(block_on(values.collect::<Vec<_>>()), vec![3]);
+    }
+
+    #[test]
+    fn values_completed() {
+        let publisher: Shared<Publisher<u64>> = Publisher::new();
+        let subscriber = publisher.subscribe();
+        publisher.send_completion(&Completion::Finished);
+        assert_eq!(block_on(subscriber.values().collect::<Vec<_>>()), vec![]);
+        assert_eq!(block_on(publisher.values().collect::<Vec<_>>()), vec![]);
+    }
+
+    #[test]
+    fn values_wake() {
+        let mut pool = LocalPool::new();
+        let publisher: Shared<Publisher<u64, String>> = Publisher::new();
+        let mut values = publisher.subscribe()
+            .map(|v| v * 10)
+            .values();
+        let received = Shared::new(Lock::new(vec![]));
+        let r = Shared::clone(&received);
+        let completion = Shared::new(Lock::new(None));
+        let completion_ref = Shared::clone(&completion);
+        pool.spawner()
+            .spawn_local(async move {
+                while let Some(v) = values.next().await {
+                    r.lock().unwrap().push(v);
+                }
+                *completion_ref.lock().unwrap() = values.completion();
+            })
+            .unwrap();
+        pool.run_until_stalled();
+        assert!(received.lock().unwrap().is_empty());
+        publisher.send_value(&1);
+        pool.run_until_stalled();
+        assert_eq!(*received.lock().unwrap(), vec![10]);
+        publisher.send_completion(&Completion::Failure("failed".into()));
+        pool.run_until_stalled();
+        assert_eq!(*completion.lock().unwrap(), Some(Completion::Failure("failed".to_string())));
+    }
+
+    #[test]
+    fn from_future() {
+        let mut pool = LocalPool::new();
+        let (sender, receiver) = oneshot::channel::<u64>();
+        let (publisher, task) = Publisher::from_future(async move {
+            receiver.await.map_err(|_| "cancelled".to_string())
+        });
+        let values = publisher.values();
+        pool.spawner().spawn_local(task).unwrap();
+        pool.run_until_stalled();
+        assert!(!publisher.is_completed());
+        sender.send(5).unwrap();
+        pool.run_until_stalled();
+        assert!(publisher.is_completed());
+        assert_eq!(block_on(values.collect::<Vec<_>>()), vec![5]);
+    }
+
+    #[test]
+    fn from_stream() {
+        let items: Vec<Result<u64, String>> = vec![Ok(1), Ok(2), Err("failed".into()), Ok(3)];
+        let (publisher, task) = Publisher::from_stream(stream::iter(items));
+        // the stream is drained before anything polls the values, so they are taken without waiting for demand
+        let mut values = publisher.subscribe().values();
+        block_on(task);
+        assert_eq!(block_on((&mut values).collect::<Vec<_>>()), vec![1, 2]);
+        assert_eq!(values.completion(), Some(Completion::Failure("failed".to_string())));
+    }
+}
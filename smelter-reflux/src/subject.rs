@@ -0,0 +1,173 @@
+use std::convert::Infallible;
+
+use super::{AnyCancellable, Completion, Lock, Publish, Publisher, Shared, Subscriber, Threadsafe};
+
+// broadcasts sent values to the current subscribers only
+pub struct PassthroughSubject<T, E = Infallible> {
+    publisher: Shared<Publisher<T, E>>,
+}
+
+impl<T, E> PassthroughSubject<T, E> where T: Threadsafe, T: 'static, E: Clone, E: Threadsafe, E: 'static {
+    pub fn new() -> Shared<Self> {
+        let subject = Self {
+            publisher: Publisher::new(),
+        };
+        Shared::new(subject)
+    }
+
+    pub fn publisher(&self) -> &Shared<Publisher<T, E>> {
+        &self.publisher
+    }
+
+    pub fn send(&self, v: &T) {
+        self.publisher.send_value(v);
+    }
+
+    pub fn subscribe(&self) -> Shared<Subscriber<T, E>> {
+        self.publisher.subscribe()
+    }
+}
+
+impl<T, E> Publish for PassthroughSubject<T, E> where T: Threadsafe, T: 'static, E: Clone, E: Threadsafe, E: 'static {
+    type Output = T;
+    type Failure = E;
+
+    fn receive_subscriber(&self, subscriber: &Shared<Subscriber<T, E>>) -> AnyCancellable {
+        self.publisher.receive_subscriber(subscriber)
+    }
+
+    fn send_value(&self, v: &T) {
+        self.publisher.send_value(v);
+    }
+
+    fn send_completion(&self, completion: &Completion<E>) {
+        self.publisher.send_completion(completion);
+    }
+}
+
+// holds a value that is replayed to every new subscriber before the values sent after it
+pub struct CurrentValueSubject<T, E = Infallible> {
+    publisher: Shared<Publisher<T, E>>,
+    value: Shared<Lock<T>>,
+    // the sink storing the value, so that sends through the publisher update it too
+    _binding: AnyCancellable,
+}
+
+impl<T, E> CurrentValueSubject<T, E> where T: Clone, T: Threadsafe, T: 'static, E: Clone, E: Threadsafe, E: 'static {
+    pub fn new(value: T) -> Shared<Self> {
+        let publisher = Publisher::with_current_value(&value);
+        let value = Shared::new(Lock::new(value));
+        let value_ref = Shared::clone(&value);
+        // subscribed first, the value is up to date before any other subscriber sees it
+        let binding = publisher.subscribe()
+            .sink(move |v| {
+                let v = v.clone();
+                if let Ok(mut guard) = value_ref.lock() {
+                    *guard = v;
+                }
+            });
+        let subject = Self {
+            publisher,
+            value,
+            _binding: binding,
+        };
+        Shared::new(subject)
+    }
+
+    // subscribing through the publisher replays the current value as well
+    pub fn publisher(&self) -> &Shared<Publisher<T, E>> {
+        &self.publisher
+    }
+
+    pub fn value(&self) -> T {
+        super::read(&self.value)
+    }
+
+    pub fn send(&self, v: &T) {
+        self.publisher.send_value(v);
+    }
+}
+
+impl<T, E> Publish for CurrentValueSubject<T, E> where T: Clone, T: Threadsafe, T: 'static, E: Clone, E: Threadsafe, E: 'static {
+    type Output = T;
+    type Failure = E;
+
+    fn receive_subscriber(&self, subscriber: &Shared<Subscriber<T, E>>) -> AnyCancellable {
+        self.publisher.receive_subscriber(subscriber)
+    }
+
+    fn send_value(&self, v: &T) {
+        self.publisher.send_value(v);
+    }
+
+    fn send_completion(&self, completion: &Completion<E>) {
+        self.publisher.send_completion(completion);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+    use super::super::testing::*;
+
+    #[test]
+    fn passthrough() {
+        let subject: Shared<PassthroughSubject<u64>> = PassthroughSubject::new();
+        subject.send(&1);
+        let subscriber = Subscriber::new();
+        let recorded = TestSubscriber::new(&subscriber);
+        let _subscription = subject.receive_subscriber(&subscriber);
+        subject.send(&2);
+        let late = Subscriber::new();
+        let late_recorded = TestSubscriber::new(&late);
+        let _late_subscription = subject.receive_subscriber(&late);
+        subject.send(&3);
+        assert_eq!(recorded.values(), vec![2, 3]);
+        assert_eq!(late_recorded.values(), vec![3]);
+    }
+
+    #[test]
+    fn current_value() {
+        let subject: Shared<CurrentValueSubject<u64>> = CurrentValueSubject::new(1);
+        assert_eq!(subject.value(), 1);
+        let subscriber = Subscriber::new();
+        let recorded = TestSubscriber::new(&subscriber);
+        let _subscription = subject.receive_subscriber(&subscriber);
+        subject.send(&2);
+        let late = Subscriber::new();
+        let late_recorded = TestSubscriber::new(&late);
+        let _late_subscription = subject.receive_subscriber(&late);
+        subject.send(&3);
+        assert_eq!(recorded.values(), vec![1, 2, 3]);
+        assert_eq!(late_recorded.values(), vec![2, 3]);
+        assert_eq!(subject.value(), 3);
+    }
+
+    #[test]
+    fn current_value_through_publisher() {
+        let subject: Shared<CurrentValueSubject<&str>> = CurrentValueSubject::new("a");
+        let subscriber: Shared<Subscriber<&str>> = Subscriber::new();
+        let values = Shared::new(Lock::new(vec![]));
+        let r = Shared::clone(&values);
+        let _sink = subscriber.map(|v| v.to_uppercase()).sink(move |v| r.lock().unwrap().push(v.clone()));
+        let _subscription = subject.publisher().receive_subscriber(&subscriber);
+        subject.send(&"b");
+        assert_eq!(*values.lock().unwrap(), vec!["A", "B"]);
+    }
+
+    #[test]
+    fn current_value_demand() {
+        let subject: Shared<CurrentValueSubject<u64>> = CurrentValueSubject::new(1);
+        let subscriber: Shared<Subscriber<u64>> = Subscriber::with_demand(Demand::nothing());
+        let values = Shared::new(Lock::new(vec![]));
+        let r = Shared::clone(&values);
+        let _sink = subscriber.sink(move |v| r.lock().unwrap().push(*v));
+        let _subscription = subject.receive_subscriber(&subscriber);
+        // the replayed value counts against demand like any other
+        assert!(values.lock().unwrap().is_empty());
+        subject.send(&2);
+        subscriber.request(Demand::max(1));
+        subject.send(&3);
+        assert_eq!(*values.lock().unwrap(), vec![3]);
+    }
+}
@@ -0,0 +1,47 @@
+// `Arc` and `Mutex`, values, sinks and closures handed to operators are `Send + Sync`
+#[macro_use]
+mod shared;
+// the same files make up the other flavours
+#[allow(clippy::duplicate_mod)]
+#[path = "core.rs"]
+mod reflux;
+pub use self::reflux::*;
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[test]
+    fn publish_from_threads() {
+        const THREADS: u64 = 8;
+        const VALUES: u64 = 10_000;
+        for policy in [ReentrancyPolicy::Nested, ReentrancyPolicy::Enqueue] {
+            let publisher: Shared<Publisher<u64>> = Publisher::with_reentrancy(policy);
+            let subscriber: Shared<Subscriber<u64>> = Subscriber::new();
+            let values = Shared::new(Mutex::new(vec![]));
+            let r = Shared::clone(&values);
+            let _sink = subscriber
+                .map(|v| v * 2)
+                .sink(move |v| r.lock().unwrap().push(*v));
+            let _subscription = publisher.receive_subscriber(&subscriber);
+            let threads: Vec<_> = (0..THREADS)
+                .map(|thread| {
+                    let publisher = Shared::clone(&publisher);
+                    std::thread::spawn(move || {
+                        for v in 0..VALUES {
+                            publisher.send_value(&(thread * VALUES + v));
+                        }
+                    })
+                })
+                .collect();
+            for thread in threads {
+                thread.join().unwrap();
+            }
+            let mut values = values.lock().unwrap().clone();
+            values.sort();
+            assert_eq!(values, (0..THREADS * VALUES).map(|v| v * 2).collect::<Vec<_>>());
+        }
+    }
+}
@@ -0,0 +1,22 @@
+pub use std::sync::{Arc as Shared, Weak as WeakShared};
+
+pub(crate) use std::sync::Mutex as Lock;
+
+// a poisoned lock still holds the last value that was stored whole
+pub(crate) fn read<T>(lock: &Lock<T>) -> T where T: Clone {
+    lock.lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .clone()
+}
+
+// values, sinks and closures handed to operators have to be `Send + Sync`
+pub trait Threadsafe: Send + Sync {}
+
+impl<T> Threadsafe for T where T: Send, T: Sync, T: ?Sized {}
+
+// a trait object bounded like `Threadsafe`, which cannot be named in one,
+// or only `Send` after `send` for closures run once
+macro_rules! threadsafe_dyn {
+    (send $($bounds:tt)*) => { dyn $($bounds)* + Send };
+    ($($bounds:tt)*) => { dyn $($bounds)* + Send + Sync };
+}
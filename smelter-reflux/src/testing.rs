@@ -0,0 +1,279 @@
+use std::{collections::HashMap, convert::Infallible, time::Duration};
+
+use super::{AnyCancellable, Completion, Lock, Publish, Publisher, Scheduler, Shared, Subscriber, Threadsafe};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Event<T, E> {
+    Value(T),
+    Completion(Completion<E>),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Recorded<T, E> {
+    pub time: Duration,
+    pub event: Event<T, E>,
+}
+
+impl<T, E> Subscriber<T, E> where T: Clone, T: Threadsafe, T: 'static, E: Clone, E: Threadsafe, E: 'static {
+    // collects what this subscriber receives, stamped with the time of the scheduler
+    pub fn record<S>(self: &Shared<Self>, scheduler: &Shared<S>) -> TestSubscriber<T, E> where S: Scheduler, S: Threadsafe, S: 'static {
+        let scheduler = Shared::clone(scheduler);
+        TestSubscriber::with_clock(self, move || scheduler.now())
+    }
+}
+
+pub struct TestSubscriber<T, E = Infallible> {
+    subscriber: Shared<Subscriber<T, E>>,
+    events: Shared<Lock<Vec<Recorded<T, E>>>>,
+    _sinks: Vec<AnyCancellable>,
+}
+
+impl<T, E> TestSubscriber<T, E> where T: Clone, T: Threadsafe, T: 'static, E: Clone, E: Threadsafe, E: 'static {
+    // collects what the subscriber receives without a clock, every event is at time zero
+    pub fn new(subscriber: &Shared<Subscriber<T, E>>) -> Self {
+        Self::with_clock(subscriber, || Duration::ZERO)
+    }
+
+    fn with_clock<C>(subscriber: &Shared<Subscriber<T, E>>, clock: C) -> Self where C: Fn() -> Duration, C: Threadsafe, C: 'static {
+        let events: Shared<Lock<Vec<Recorded<T, E>>>> = Shared::new(Lock::new(vec![]));
+        let clock = Shared::new(clock);
+        let events_ref = Shared::clone(&events);
+        let clock_ref = Shared::clone(&clock);
+        let value = subscriber.sink(move |v| {
+            if let Ok(mut guard) = events_ref.lock() {
+                guard.push(Recorded { time: clock_ref(), event: Event::Value(v.clone()) });
+            }
+        });
+        let events_ref = Shared::clone(&events);
+        let completion = subscriber.sink_completion(move |completion| {
+            if let Ok(mut guard) = events_ref.lock() {
+                guard.push(Recorded { time: clock(), event: Event::Completion(completion.clone()) });
+            }
+        });
+        Self {
+            subscriber: Shared::clone(subscriber),
+            events,
+            _sinks: vec![value, completion],
+        }
+    }
+}
+
+impl<T, E> TestSubscriber<T, E> where T: Clone, E: Clone {
+    pub fn subscriber(&self) -> &Shared<Subscriber<T, E>> {
+        &self.subscriber
+    }
+
+    pub fn events(&self) -> Vec<Recorded<T, E>> {
+        self.events.lock()
+            .map(|guard| guard.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn values(&self) -> Vec<T> {
+        self.events()
+            .into_iter()
+            .filter_map(|recorded| match recorded.event {
+                Event::Value(v) => Some(v),
+                Event::Completion(_) => None,
+            })
+            .collect()
+    }
+
+    pub fn completion(&self) -> Option<Completion<E>> {
+        self.events()
+            .into_iter()
+            .find_map(|recorded| match recorded.event {
+                Event::Value(_) => None,
+                Event::Completion(completion) => Some(completion),
+            })
+    }
+}
+
+// reads marble diagrams such as "-a-(bc)-|", one character per frame of time:
+// `-` lets a frame pass, `|` finishes, `#` fails with the error, spaces are ignored,
+// any other character sends its value and a group in parentheses sends all of its values in one frame
+pub struct Marbles<T, E = Infallible> {
+    frame: Duration,
+    values: HashMap<char, T>,
+    error: Option<E>,
+}
+
+impl<T, E> Marbles<T, E> where T: Clone, E: Clone {
+    pub fn new(frame: Duration) -> Self {
+        Self {
+            frame,
+            values: HashMap::new(),
+            error: None,
+        }
+    }
+
+    pub fn value(mut self, name: char, v: T) -> Self {
+        self.values.insert(name, v);
+        self
+    }
+
+    pub fn error(mut self, e: E) -> Self {
+        self.error = Some(e);
+        self
+    }
+
+    // the events of the diagram timed from zero, panics on a character without a value
+    pub fn parse(&self, marbles: &str) -> Vec<Recorded<T, E>> {
+        let mut events = vec![];
+        let mut frame = 0u32;
+        let mut group = false;
+        for name in marbles.chars() {
+            let time = self.frame * frame;
+            match name {
+                ' ' => continue,
+                '-' => {},
+                '(' => group = true,
+                ')' => group = false,
+                '|' => events.push(Recorded { time, event: Event::Completion(Completion::Finished) }),
+                '#' => {
+                    let e = self.error.clone()
+                        .unwrap_or_else(|| panic!("no error given for `#` in {:?}", marbles));
+                    events.push(Recorded { time, event: Event::Completion(Completion::Failure(e)) });
+                },
+                name => {
+                    let v = self.values.get(&name)
+                        .unwrap_or_else(|| panic!("no value given for `{}` in {:?}", name, marbles));
+                    events.push(Recorded { time, event: Event::Value(v.clone()) });
+                },
+            }
+            if !group {
+                frame += 1;
+            }
+        }
+        events
+    }
+}
+
+impl<T, E> Marbles<T, E> where T: Clone, T: Threadsafe, T: 'static, E: Clone, E: Threadsafe, E: 'static {
+    // sends the events of the diagram as the scheduler reaches them, timed from its current time
+    pub fn publisher<S>(&self, marbles: &str, scheduler: &Shared<S>) -> TestPublisher<T, E> where S: Scheduler {
+        let publisher: Shared<Publisher<T, E>> = Publisher::new();
+        let scheduled = self.parse(marbles)
+            .into_iter()
+            .map(|recorded| {
+                let publisher = Shared::clone(&publisher);
+                scheduler.schedule_after(recorded.time, Box::new(move || {
+                    match &recorded.event {
+                        Event::Value(v) => publisher.send_value(v),
+                        Event::Completion(completion) => publisher.send_completion(completion),
+                    }
+                }))
+            })
+            .collect();
+        TestPublisher {
+            publisher,
+            _scheduled: scheduled,
+        }
+    }
+}
+
+// a publisher driven by a marble diagram, nothing more is sent once it is dropped
+pub struct TestPublisher<T, E = Infallible> {
+    publisher: Shared<Publisher<T, E>>,
+    _scheduled: Vec<AnyCancellable>,
+}
+
+impl<T, E> TestPublisher<T, E> where T: Threadsafe, T: 'static, E: Clone, E: Threadsafe, E: 'static {
+    pub fn publisher(&self) -> &Shared<Publisher<T, E>> {
+        &self.publisher
+    }
+
+    pub fn subscribe(&self) -> Shared<Subscriber<T, E>> {
+        self.publisher.subscribe()
+    }
+}
+
+impl<T, E> Publish for TestPublisher<T, E> where T: Threadsafe, T: 'static, E: Clone, E: Threadsafe, E: 'static {
+    type Output = T;
+    type Failure = E;
+
+    fn receive_subscriber(&self, subscriber: &Shared<Subscriber<T, E>>) -> AnyCancellable {
+        self.publisher.receive_subscriber(subscriber)
+    }
+
+    fn send_value(&self, v: &T) {
+        self.publisher.send_value(v);
+    }
+
+    fn send_completion(&self, completion: &Completion<E>) {
+        self.publisher.send_completion(completion);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::super::*;
+    use super::super::testing::*;
+
+    const FRAME: Duration = Duration::from_millis(10);
+
+    #[test]
+    fn parse() {
+        let marbles: Marbles<u64> = Marbles::new(FRAME)
+            .value('a', 1)
+            .value('b', 2)
+            .value('c', 3);
+        assert_eq!(marbles.parse("-a-(bc) -|"), vec![
+            Recorded { time: FRAME, event: Event::Value(1) },
+            Recorded { time: FRAME * 3, event: Event::Value(2) },
+            Recorded { time: FRAME * 3, event: Event::Value(3) },
+            Recorded { time: FRAME * 5, event: Event::Completion(Completion::Finished) },
+        ]);
+    }
+
+    #[test]
+    fn record() {
+        let scheduler = VirtualTimeScheduler::new();
+        let marbles: Marbles<u64, String> = Marbles::new(FRAME)
+            .value('a', 1)
+            .value('b', 2)
+            .value('x', 10)
+            .value('y', 20)
+            .error("failed".into());
+        let source = marbles.publisher("-a--b-#", &scheduler);
+        let recorded = source.subscribe()
+            .map(|v| v * 10)
+            .record(&scheduler);
+        scheduler.run();
+        assert_eq!(recorded.events(), marbles.parse("-x--y-#"));
+        assert_eq!(recorded.values(), vec![10, 20]);
+        assert_eq!(recorded.completion(), Some(Completion::Failure("failed".to_string())));
+    }
+
+    #[test]
+    fn debounce() {
+        let scheduler = VirtualTimeScheduler::new();
+        let marbles: Marbles<char> = Marbles::new(FRAME)
+            .value('a', 'a')
+            .value('b', 'b')
+            .value('c', 'c');
+        let source = marbles.publisher("-ab-----c--|", &scheduler);
+        let recorded = source.subscribe()
+            .debounce(FRAME * 2, &scheduler)
+            .record(&scheduler);
+        scheduler.run();
+        assert_eq!(recorded.events(), marbles.parse("----b-----c|"));
+    }
+
+    #[test]
+    fn dropped_publisher() {
+        let scheduler = VirtualTimeScheduler::new();
+        let marbles: Marbles<u64> = Marbles::new(FRAME)
+            .value('a', 1);
+        let source = marbles.publisher("a-a-|", &scheduler);
+        let recorded = source.subscribe()
+            .record(&scheduler);
+        scheduler.advance_by(FRAME);
+        drop(source);
+        scheduler.run();
+        assert_eq!(recorded.values(), vec![1]);
+        assert!(!recorded.subscriber().is_completed());
+    }
+}
@@ -0,0 +1,218 @@
+use std::{cell::RefCell, fmt::Write, sync::{atomic::{AtomicUsize, Ordering}, Mutex}};
+
+// publishers and subscribers are told apart by their address, which is unique while they are alive
+// the number of live `Tracing` guards, tracing is on while there is any
+static ENABLED: AtomicUsize = AtomicUsize::new(0);
+static GRAPH: Mutex<TraceGraph> = Mutex::new(TraceGraph { nodes: vec![], edges: vec![] });
+
+thread_local! {
+    // subscribers whose sinks are running on this thread, innermost last
+    static DELIVERING: RefCell<Vec<usize>> = const { RefCell::new(vec![]) };
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TraceNodeKind {
+    Publisher,
+    Subscriber,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TraceNode {
+    pub id: usize,
+    pub kind: TraceNodeKind,
+    pub value_type: &'static str,
+    // values sent by a publisher or received by a subscriber
+    pub values: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TraceEdgeKind {
+    // from a publisher to a subscriber attached to it
+    Subscription,
+    // from a subscriber to a publisher one of its sinks sent a value to
+    Sink,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TraceEdge {
+    pub from: usize,
+    pub to: usize,
+    pub kind: TraceEdgeKind,
+    // the subscription an edge of that kind stands for
+    subscription: Option<usize>,
+}
+
+// the live publishers and subscribers created since tracing was enabled
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TraceGraph {
+    pub nodes: Vec<TraceNode>,
+    pub edges: Vec<TraceEdge>,
+}
+
+impl TraceGraph {
+    pub fn node(&self, id: usize) -> Option<&TraceNode> {
+        self.nodes.iter().find(|node| node.id == id)
+    }
+
+    pub fn edges_from(&self, id: usize) -> impl Iterator<Item = &TraceEdge> {
+        self.edges.iter().filter(move |edge| edge.from == id)
+    }
+
+    // the graph in Graphviz format
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph reflux {\n");
+        for node in self.nodes.iter() {
+            let shape = match node.kind {
+                TraceNodeKind::Publisher => "box",
+                TraceNodeKind::Subscriber => "ellipse",
+            };
+            let _ = writeln!(dot, "  n{} [shape={}, label=\"{:?}<{}>\\n{} values\"];", node.id, shape, node.kind, node.value_type, node.values);
+        }
+        for edge in self.edges.iter() {
+            let style = match edge.kind {
+                TraceEdgeKind::Subscription => "solid",
+                TraceEdgeKind::Sink => "dashed",
+            };
+            let _ = writeln!(dot, "  n{} -> n{} [style={}];", edge.from, edge.to, style);
+        }
+        dot.push('}');
+        dot
+    }
+
+    fn contains(&self, id: usize) -> bool {
+        self.node(id).is_some()
+    }
+}
+
+// starts recording publishers and subscribers created from now on, at a small cost on every value,
+// until every guard returned is dropped
+pub fn enable_tracing() -> Tracing {
+    ENABLED.fetch_add(1, Ordering::SeqCst);
+    Tracing {
+        _private: (),
+    }
+}
+
+#[must_use = "tracing is disabled again once the guard is dropped"]
+pub struct Tracing {
+    _private: (),
+}
+
+impl Drop for Tracing {
+    fn drop(&mut self) {
+        ENABLED.fetch_sub(1, Ordering::SeqCst);
+        // checked under the lock, a guard taken meanwhile keeps what was recorded
+        update(|graph| {
+            if !is_enabled() {
+                *graph = TraceGraph::default();
+            }
+        });
+    }
+}
+
+pub fn trace_graph() -> TraceGraph {
+    GRAPH.lock()
+        .map(|guard| guard.clone())
+        .unwrap_or_default()
+}
+
+fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed) > 0
+}
+
+fn update<F>(f: F) where F: FnOnce(&mut TraceGraph) {
+    if let Ok(mut guard) = GRAPH.lock() {
+        f(&mut guard);
+    }
+}
+
+pub(crate) fn created<T: ?Sized>(id: usize, kind: TraceNodeKind) {
+    if !is_enabled() {
+        return;
+    }
+    update(|graph| graph.nodes.push(TraceNode {
+        id,
+        kind,
+        value_type: std::any::type_name::<T>(),
+        values: 0,
+    }));
+}
+
+pub(crate) fn dropped(id: usize) {
+    if !is_enabled() {
+        return;
+    }
+    update(|graph| {
+        graph.nodes.retain(|node| node.id != id);
+        graph.edges.retain(|edge| edge.from != id && edge.to != id);
+    });
+}
+
+pub(crate) fn subscribed(subscription: usize, publisher: usize, subscriber: usize) {
+    if !is_enabled() {
+        return;
+    }
+    update(|graph| {
+        if graph.contains(publisher) && graph.contains(subscriber) {
+            graph.edges.push(TraceEdge {
+                from: publisher,
+                to: subscriber,
+                kind: TraceEdgeKind::Subscription,
+                subscription: Some(subscription),
+            });
+        }
+    });
+}
+
+pub(crate) fn unsubscribed(subscription: usize) {
+    if !is_enabled() {
+        return;
+    }
+    update(|graph| graph.edges.retain(|edge| edge.subscription != Some(subscription)));
+}
+
+pub(crate) fn sent(publisher: usize) {
+    if !is_enabled() {
+        return;
+    }
+    let subscriber = DELIVERING.with(|delivering| delivering.borrow().last().copied());
+    update(|graph| {
+        let Some(node) = graph.nodes.iter_mut().find(|node| node.id == publisher) else {
+            return
+        };
+        node.values += 1;
+        let Some(subscriber) = subscriber else {
+            return
+        };
+        let linked = graph.edges.iter()
+            .any(|edge| edge.kind == TraceEdgeKind::Sink && edge.from == subscriber && edge.to == publisher);
+        if !linked && graph.contains(subscriber) {
+            graph.edges.push(TraceEdge {
+                from: subscriber,
+                to: publisher,
+                kind: TraceEdgeKind::Sink,
+                subscription: None,
+            });
+        }
+    });
+}
+
+// runs the sinks of a subscriber, a publisher sent to from inside them is linked to it
+pub(crate) fn delivering<F>(subscriber: usize, f: F) where F: FnOnce() {
+    if !is_enabled() {
+        f();
+        return;
+    }
+    update(|graph| {
+        if let Some(node) = graph.nodes.iter_mut().find(|node| node.id == subscriber) {
+            node.values += 1;
+        }
+    });
+    DELIVERING.with(|delivering| delivering.borrow_mut().push(subscriber));
+    f();
+    DELIVERING.with(|delivering| delivering.borrow_mut().pop());
+}
+
+pub(crate) fn address<T>(value: &T) -> usize {
+    value as *const T as usize
+}
@@ -1,4 +1,4 @@
-use std::{borrow::BorrowMut, sync::{Arc, Mutex}};
+use std::sync::Arc;
 
 use crate::{DOMDocument, DOMElement, DOMElementType};
 
@@ -15,6 +15,12 @@ pub struct DocumentBuilder {
     current_element: Option<Arc<DOMElement>>,
 }
 
+impl Default for DocumentBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl DocumentBuilder {
     pub fn new() -> Self {
         Self {
@@ -24,6 +30,7 @@ impl DocumentBuilder {
         }
     }
 
+    #[allow(clippy::arc_with_non_send_sync)]
     pub fn build(self) -> Arc<DOMDocument> {
         Arc::new(DOMDocument::new(self.root_elements))
     }
@@ -44,7 +51,7 @@ impl DOMContext for DocumentBuilder {
         self.current_element = self.parent_stack.pop();
     }
 
-    fn create_element<'a>(&'a mut self, tp: DOMElementType) -> Arc<DOMElement> {
+    fn create_element(&mut self, tp: DOMElementType) -> Arc<DOMElement> {
         let element = DOMElement::new(tp);
         if let Some(current_element) = self.current_element.as_ref() {
             current_element.push_child(&element);
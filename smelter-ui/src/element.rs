@@ -1,6 +1,6 @@
 use std::{borrow::BorrowMut, sync::{Arc, Mutex}};
 
-use smelter_reflux::{Property, Publisher, Subscriber};
+use smelter_reflux::{AnyCancellable, CurrentValueSubject, Publisher, Shared};
 
 pub enum DOMElementType {
     Div,
@@ -14,6 +14,8 @@ pub struct DOMElement {
 }
 
 impl DOMElement {
+    // elements are shared through `Arc` but hold single-threaded reactive state
+    #[allow(clippy::arc_with_non_send_sync)]
     pub(crate) fn new(element_type: DOMElementType) -> Arc<Self> {
         let state = DOMElementState::new(&element_type);
         let element = Self {
@@ -37,22 +39,34 @@ impl DOMElement {
         }
     }
 
+    pub(crate) fn push_attribute(&self, key: String, value: String) {
+        if let Ok(mut guard) = self.state.lock() {
+            let state = guard.borrow_mut();
+            state.push_attribute(key, value);
+        }
+    }
+
+    pub(crate) fn push_cancellable(&self, cancellable: AnyCancellable) {
+        if let Ok(mut guard) = self.state.lock() {
+            let state = guard.borrow_mut();
+            state.push_cancellable(cancellable);
+        }
+    }
+
     pub(crate) fn element_type(&self) -> &DOMElementType {
         &self.element_type
     }
 
-    pub(crate) fn text_property(&self) -> Option<Arc<Property<Option<String>>>> {
+    pub(crate) fn text_subject(&self) -> Option<Shared<CurrentValueSubject<Option<String>>>> {
         self.state.lock()
             .ok()
-            .map(|v| v.text_property().clone())
-            .flatten()
+            .and_then(|v| v.text_subject().clone())
     }
 
-    pub(crate) fn onclick_publisher(&self) -> Option<Arc<Publisher<()>>> {
+    pub(crate) fn onclick_publisher(&self) -> Option<Shared<Publisher<()>>> {
         self.state.lock()
             .ok()
-            .map(|v| v.onclick_publisher().clone())
-            .flatten()
+            .and_then(|v| v.onclick_publisher().clone())
     }
 
     pub(crate) fn styles(&self) -> Option<Vec<(String, String)>> {
@@ -60,30 +74,40 @@ impl DOMElement {
             .ok()
             .map(|v| v.styles().clone())
     }
+
+    pub(crate) fn attributes(&self) -> Option<Vec<(String, String)>> {
+        self.state.lock()
+            .ok()
+            .map(|v| v.attributes().clone())
+    }
 }
 
 pub struct DOMElementState {
     pub(crate) children: Vec<Arc<DOMElement>>,
-    text_property: Option<Arc<Property<Option<String>>>>,
-    onclick_publisher: Option<Arc<Publisher<()>>>,
+    text_subject: Option<Shared<CurrentValueSubject<Option<String>>>>,
+    onclick_publisher: Option<Shared<Publisher<()>>>,
     styles: Vec<(String, String)>,
+    attributes: Vec<(String, String)>,
+    cancellables: Vec<AnyCancellable>,
 }
 
 impl DOMElementState {
     fn new(tp: &DOMElementType) -> Self {
         Self {
             children: vec![],
-            text_property: tp.has_text().then(|| Property::new(None)),
-            onclick_publisher: tp.has_onclick().then(|| Publisher::new()),
+            text_subject: tp.has_text().then(|| CurrentValueSubject::new(None)),
+            onclick_publisher: tp.has_onclick().then(Publisher::new),
             styles: vec![],
+            attributes: vec![],
+            cancellables: vec![],
         }
     }
 
-    fn text_property(&self) -> &Option<Arc<Property<Option<String>>>> {
-        &self.text_property
+    fn text_subject(&self) -> &Option<Shared<CurrentValueSubject<Option<String>>>> {
+        &self.text_subject
     }
 
-    fn onclick_publisher(&self) -> &Option<Arc<Publisher<()>>> {
+    fn onclick_publisher(&self) -> &Option<Shared<Publisher<()>>> {
         &self.onclick_publisher
     }
 
@@ -91,6 +115,10 @@ impl DOMElementState {
         &self.styles
     }
 
+    fn attributes(&self) -> &Vec<(String, String)> {
+        &self.attributes
+    }
+
     fn push_child(&mut self, element: &Arc<DOMElement>) {
         self.children.push(Arc::clone(element));
     }
@@ -98,22 +126,30 @@ impl DOMElementState {
     fn push_style(&mut self, key: String, value: String) {
         self.styles.push((key, value));
     }
+
+    fn push_attribute(&mut self, key: String, value: String) {
+        self.attributes.push((key, value));
+    }
+
+    fn push_cancellable(&mut self, cancellable: AnyCancellable) {
+        self.cancellables.push(cancellable);
+    }
 }
 
 impl DOMElementType {
     fn has_text(&self) -> bool {
-        match &self {
-            &DOMElementType::Div => true,
-            &DOMElementType::Button => true,
-            &DOMElementType::Paragraph => true,
+        match self {
+            DOMElementType::Div => true,
+            DOMElementType::Button => true,
+            DOMElementType::Paragraph => true,
         }
     }
 
     fn has_onclick(&self) -> bool {
-        match &self {
-            &DOMElementType::Div => true,
-            &DOMElementType::Button => true,
-            &DOMElementType::Paragraph => true,
+        match self {
+            DOMElementType::Div => true,
+            DOMElementType::Button => true,
+            DOMElementType::Paragraph => true,
         }
     }
 }
@@ -0,0 +1,509 @@
+use std::{fmt::Write, sync::{Arc, Mutex, Weak}};
+
+use crate::Renderer;
+
+// a slot of a removed node is reused, the generation tells the nodes in it apart
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct HeadlessNode {
+    index: usize,
+    generation: u64,
+}
+
+// an in-memory node tree for running documents without a browser,
+// events are dispatched synthetically and bubble up like in the DOM
+pub struct HeadlessRenderer {
+    state: Arc<Mutex<HeadlessState>>,
+}
+
+struct HeadlessState {
+    nodes: Vec<HeadlessSlot>,
+    // slots of removed nodes, free to be taken by new ones
+    free: Vec<usize>,
+    next_listener_id: u64,
+}
+
+struct HeadlessSlot {
+    generation: u64,
+    data: Option<HeadlessNodeData>,
+}
+
+struct HeadlessNodeData {
+    name: String,
+    parent: Option<HeadlessNode>,
+    children: Vec<HeadlessNode>,
+    text: Option<String>,
+    styles: Vec<(String, String)>,
+    attributes: Vec<(String, String)>,
+    listeners: Vec<RegisteredListener>,
+}
+
+struct RegisteredListener {
+    id: u64,
+    event: String,
+    callback: Arc<dyn Fn()>,
+}
+
+impl HeadlessNodeData {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.into(),
+            parent: None,
+            children: vec![],
+            text: None,
+            styles: vec![],
+            attributes: vec![],
+            listeners: vec![],
+        }
+    }
+}
+
+impl Default for HeadlessRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HeadlessRenderer {
+    // starts with an empty `body` as the root, the listeners it keeps are not `Send`
+    #[allow(clippy::arc_with_non_send_sync)]
+    pub fn new() -> Self {
+        let state = HeadlessState {
+            nodes: vec![HeadlessSlot { generation: 0, data: Some(HeadlessNodeData::new("body")) }],
+            free: vec![],
+            next_listener_id: 0,
+        };
+        Self {
+            state: Arc::new(Mutex::new(state)),
+        }
+    }
+
+    pub fn root(&self) -> HeadlessNode {
+        HeadlessNode { index: 0, generation: 0 }
+    }
+
+    pub fn tag(&self, node: &HeadlessNode) -> Option<String> {
+        self.read(node, |data| data.name.clone())
+    }
+
+    pub fn text(&self, node: &HeadlessNode) -> Option<String> {
+        self.read(node, |data| data.text.clone())
+            .flatten()
+    }
+
+    pub fn style(&self, node: &HeadlessNode, key: &str) -> Option<String> {
+        self.read(node, |data| find(&data.styles, key))
+            .flatten()
+    }
+
+    pub fn attribute(&self, node: &HeadlessNode, key: &str) -> Option<String> {
+        self.read(node, |data| find(&data.attributes, key))
+            .flatten()
+    }
+
+    pub fn children(&self, node: &HeadlessNode) -> Vec<HeadlessNode> {
+        self.read(node, |data| data.children.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn parent(&self, node: &HeadlessNode) -> Option<HeadlessNode> {
+        self.read(node, |data| data.parent)
+            .flatten()
+    }
+
+    // queries only see nodes attached under the root, in document order
+    pub fn find_by_tag(&self, tag: &str) -> Vec<HeadlessNode> {
+        self.find(|data| data.name == tag)
+    }
+
+    pub fn find_by_text(&self, text: &str) -> Vec<HeadlessNode> {
+        self.find(|data| data.text.as_deref() == Some(text))
+    }
+
+    pub fn find_by_attribute(&self, key: &str, value: &str) -> Vec<HeadlessNode> {
+        self.find(|data| find(&data.attributes, key).as_deref() == Some(value))
+    }
+
+    // runs the click listeners of the node and then those of its ancestors
+    pub fn click(&self, node: &HeadlessNode) {
+        self.dispatch(node, "click");
+    }
+
+    pub fn dispatch(&self, node: &HeadlessNode, event: &str) {
+        // listeners run without the lock, they are free to update the tree
+        let listeners: Vec<Arc<dyn Fn()>> = self.state.lock()
+            .map(|guard| {
+                let mut listeners = vec![];
+                let mut current = Some(*node);
+                while let Some(node) = current {
+                    let Some(data) = guard.get(node) else {
+                        break
+                    };
+                    listeners.extend(data.listeners.iter()
+                        .filter(|listener| listener.event == event)
+                        .map(|listener| Arc::clone(&listener.callback)));
+                    current = data.parent;
+                }
+                listeners
+            })
+            .unwrap_or_default();
+        for listener in listeners {
+            listener();
+        }
+    }
+
+    // the subtree as indented markup, one element per line, for comparing against expectations
+    pub fn snapshot(&self, node: &HeadlessNode) -> String {
+        let mut snapshot = String::new();
+        if let Ok(guard) = self.state.lock() {
+            guard.snapshot(*node, 0, &mut snapshot);
+        }
+        snapshot
+    }
+
+    fn read<F, V>(&self, node: &HeadlessNode, f: F) -> Option<V> where F: FnOnce(&HeadlessNodeData) -> V {
+        self.state.lock()
+            .ok()
+            .and_then(|guard| guard.get(*node).map(f))
+    }
+
+    fn write<F>(&self, node: &HeadlessNode, f: F) where F: FnOnce(&mut HeadlessNodeData) {
+        if let Ok(mut guard) = self.state.lock() {
+            if let Some(data) = guard.get_mut(*node) {
+                f(data);
+            }
+        }
+    }
+
+    fn find<F>(&self, predicate: F) -> Vec<HeadlessNode> where F: Fn(&HeadlessNodeData) -> bool {
+        let mut found = vec![];
+        if let Ok(guard) = self.state.lock() {
+            guard.find(self.root(), &predicate, &mut found);
+        }
+        found
+    }
+}
+
+impl HeadlessState {
+    fn get(&self, node: HeadlessNode) -> Option<&HeadlessNodeData> {
+        self.nodes.get(node.index)
+            .filter(|slot| slot.generation == node.generation)?
+            .data.as_ref()
+    }
+
+    fn get_mut(&mut self, node: HeadlessNode) -> Option<&mut HeadlessNodeData> {
+        self.nodes.get_mut(node.index)
+            .filter(|slot| slot.generation == node.generation)?
+            .data.as_mut()
+    }
+
+    fn create(&mut self, name: &str) -> HeadlessNode {
+        let data = Some(HeadlessNodeData::new(name));
+        if let Some(index) = self.free.pop() {
+            let slot = &mut self.nodes[index];
+            slot.generation += 1;
+            slot.data = data;
+            return HeadlessNode { index, generation: slot.generation };
+        }
+        self.nodes.push(HeadlessSlot { generation: 0, data });
+        HeadlessNode { index: self.nodes.len() - 1, generation: 0 }
+    }
+
+    // frees the slots of the subtree, handing back the data so that listeners are dropped without the lock
+    fn free(&mut self, node: HeadlessNode, freed: &mut Vec<HeadlessNodeData>) {
+        let Some(data) = self.nodes.get_mut(node.index)
+            .filter(|slot| slot.generation == node.generation)
+            .and_then(|slot| slot.data.take()) else {
+            return
+        };
+        self.free.push(node.index);
+        for child in data.children.iter() {
+            self.free(*child, freed);
+        }
+        freed.push(data);
+    }
+
+    fn find<F>(&self, node: HeadlessNode, predicate: &F, found: &mut Vec<HeadlessNode>) where F: Fn(&HeadlessNodeData) -> bool {
+        let Some(data) = self.get(node) else {
+            return
+        };
+        if predicate(data) {
+            found.push(node);
+        }
+        for child in data.children.iter() {
+            self.find(*child, predicate, found);
+        }
+    }
+
+    fn snapshot(&self, node: HeadlessNode, depth: usize, snapshot: &mut String) {
+        let Some(data) = self.get(node) else {
+            return
+        };
+        let indent = "  ".repeat(depth);
+        let _ = write!(snapshot, "{}<{}", indent, data.name);
+        for (key, value) in data.attributes.iter() {
+            let _ = write!(snapshot, " {}=\"{}\"", key, value);
+        }
+        if !data.styles.is_empty() {
+            let styles: Vec<_> = data.styles.iter()
+                .map(|(key, value)| format!("{}: {}", key, value))
+                .collect();
+            let _ = write!(snapshot, " style=\"{}\"", styles.join("; "));
+        }
+        snapshot.push('>');
+        if let Some(text) = data.text.as_ref() {
+            snapshot.push_str(text);
+        }
+        if data.children.is_empty() {
+            let _ = writeln!(snapshot, "</{}>", data.name);
+            return;
+        }
+        snapshot.push('\n');
+        for child in data.children.iter() {
+            self.snapshot(*child, depth + 1, snapshot);
+        }
+        let _ = writeln!(snapshot, "{}</{}>", indent, data.name);
+    }
+
+    fn detach(&mut self, node: HeadlessNode) {
+        let parent = self.get_mut(node)
+            .and_then(|data| data.parent.take());
+        if let Some(parent) = parent.and_then(|parent| self.get_mut(parent)) {
+            parent.children.retain(|child| *child != node);
+        }
+    }
+}
+
+fn find(entries: &[(String, String)], key: &str) -> Option<String> {
+    entries.iter()
+        .find(|(k, _)| k == key)
+        .map(|(_, v)| v.clone())
+}
+
+fn replace(entries: &mut Vec<(String, String)>, key: &str, value: &str) {
+    if let Some(entry) = entries.iter_mut().find(|(k, _)| k == key) {
+        entry.1 = value.into();
+    } else {
+        entries.push((key.into(), value.into()));
+    }
+}
+
+// removes the listener from its node once dropped
+pub struct HeadlessListener {
+    state: Weak<Mutex<HeadlessState>>,
+    node: HeadlessNode,
+    id: u64,
+}
+
+impl Drop for HeadlessListener {
+    fn drop(&mut self) {
+        let Some(state) = self.state.upgrade() else {
+            return
+        };
+        // taken out so that the listener is dropped without the lock
+        let removed = state.lock()
+            .ok()
+            .and_then(|mut guard| {
+                let data = guard.get_mut(self.node)?;
+                let index = data.listeners.iter().position(|listener| listener.id == self.id)?;
+                Some(data.listeners.remove(index))
+            });
+        drop(removed);
+    }
+}
+
+impl Renderer for HeadlessRenderer {
+    type Node = HeadlessNode;
+    type Listener = HeadlessListener;
+
+    fn create_node(&self, name: &str) -> Self::Node {
+        self.state.lock()
+            .map(|mut guard| guard.create(name))
+            .unwrap_or(HeadlessNode { index: usize::MAX, generation: 0 })
+    }
+
+    fn append(&self, parent: &Self::Node, child: &Self::Node) {
+        if let Ok(mut guard) = self.state.lock() {
+            if guard.get(*parent).is_none() || guard.get(*child).is_none() {
+                return;
+            }
+            guard.detach(*child);
+            if let Some(data) = guard.get_mut(*child) {
+                data.parent = Some(*parent);
+            }
+            if let Some(data) = guard.get_mut(*parent) {
+                data.children.push(*child);
+            }
+        }
+    }
+
+    fn set_text(&self, node: &Self::Node, text: Option<&str>) {
+        // like `textContent`, the text replaces the children
+        if let Ok(mut guard) = self.state.lock() {
+            let children = guard.get(*node)
+                .map(|data| data.children.clone())
+                .unwrap_or_default();
+            for child in children {
+                guard.detach(child);
+            }
+            if let Some(data) = guard.get_mut(*node) {
+                data.text = text.map(|v| v.into());
+            }
+        }
+    }
+
+    fn set_style(&self, node: &Self::Node, key: &str, value: &str) {
+        self.write(node, |data| replace(&mut data.styles, key, value));
+    }
+
+    fn set_attribute(&self, node: &Self::Node, key: &str, value: &str) {
+        self.write(node, |data| replace(&mut data.attributes, key, value));
+    }
+
+    fn attach_listener(&self, node: &Self::Node, event: &str, listener: Box<dyn Fn()>) -> Self::Listener {
+        let id = self.state.lock()
+            .map(|mut guard| {
+                let id = guard.next_listener_id;
+                guard.next_listener_id += 1;
+                if let Some(data) = guard.get_mut(*node) {
+                    data.listeners.push(RegisteredListener {
+                        id,
+                        event: event.into(),
+                        callback: Arc::from(listener),
+                    });
+                }
+                id
+            })
+            .unwrap_or_default();
+        HeadlessListener {
+            state: Arc::downgrade(&self.state),
+            node: *node,
+            id,
+        }
+    }
+
+    // removed nodes are gone for good, their slots are freed along with their listeners
+    fn remove(&self, node: &Self::Node) {
+        let mut freed = vec![];
+        if let Ok(mut guard) = self.state.lock() {
+            guard.detach(*node);
+            guard.free(*node, &mut freed);
+        }
+        drop(freed);
+    }
+
+    fn element_children(&self, node: &Self::Node) -> Vec<Self::Node> {
+        self.children(node)
+    }
+
+    fn tag_name(&self, node: &Self::Node) -> String {
+        self.tag(node)
+            .unwrap_or_default()
+    }
+
+    fn text_content(&self, node: &Self::Node) -> Option<String> {
+        self.text(node)
+    }
+
+    fn get_style(&self, node: &Self::Node, key: &str) -> Option<String> {
+        self.style(node, key)
+    }
+
+    fn get_attribute(&self, node: &Self::Node, key: &str) -> Option<String> {
+        self.attribute(node, key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::*;
+    use smelter_reflux::*;
+
+    #[test]
+    #[allow(clippy::arc_with_non_send_sync)]
+    fn click() {
+        let mut builder = DocumentBuilder::new();
+        let context = &mut builder;
+        let mut cancellables = vec![];
+        Division::new(context).children(|context| {
+            let text_content = Publisher::new();
+            Paragraph::new(context)
+                .text("Smelter")
+                .style("font-size", "32pt")
+                .attribute("id", "title")
+                .subscribe_text(&text_content);
+            let onclick = Subscriber::new();
+            onclick
+                .scan(0, |count, _| count + 1)
+                .map(|count| Some(format!("Clicked {}", count)))
+                .bind(&text_content)
+                .store_in(&mut cancellables);
+            Button::new(context)
+                .text("Hello world!")
+                .publish_onclick(&onclick);
+        });
+        let document = builder.build();
+        let renderer = Arc::new(HeadlessRenderer::new());
+        let _instance = document.instantiate_with(&renderer, &renderer.root());
+        assert_eq!(renderer.snapshot(&renderer.root()), concat!(
+            "<body>\n",
+            "  <div>\n",
+            "    <p id=\"title\" style=\"font-size: 32pt\">Smelter</p>\n",
+            "    <button>Hello world!</button>\n",
+            "  </div>\n",
+            "</body>\n",
+        ));
+        let button = renderer.find_by_text("Hello world!")[0];
+        assert_eq!(renderer.find_by_tag("button"), vec![button]);
+        renderer.click(&button);
+        renderer.click(&button);
+        let title = renderer.find_by_attribute("id", "title")[0];
+        assert_eq!(renderer.text(&title).as_deref(), Some("Clicked 2"));
+        assert_eq!(renderer.style(&title, "font-size").as_deref(), Some("32pt"));
+        assert_eq!(renderer.snapshot(&title), "<p id=\"title\" style=\"font-size: 32pt\">Clicked 2</p>\n");
+    }
+
+    #[test]
+    fn listeners() {
+        let renderer = HeadlessRenderer::new();
+        let parent = renderer.create_node("div");
+        let child = renderer.create_node("button");
+        renderer.append(&renderer.root(), &parent);
+        renderer.append(&parent, &child);
+        let clicks = Arc::new(std::sync::Mutex::new(vec![]));
+        let (a, b) = (Arc::clone(&clicks), Arc::clone(&clicks));
+        let parent_listener = renderer.attach_listener(&parent, "click", Box::new(move || a.lock().unwrap().push("div")));
+        let _child_listener = renderer.attach_listener(&child, "click", Box::new(move || b.lock().unwrap().push("button")));
+        renderer.click(&child);
+        drop(parent_listener);
+        renderer.click(&child);
+        assert_eq!(*clicks.lock().unwrap(), vec!["button", "div", "button"]);
+        renderer.remove(&parent);
+        assert!(renderer.find_by_tag("button").is_empty());
+        assert_eq!(renderer.tag(&child), None);
+    }
+
+    #[test]
+    fn remove() {
+        // mounting and unmounting over and over reuses the slots of the removed nodes
+        let renderer = HeadlessRenderer::new();
+        let mut removed = vec![];
+        for _ in 0..100 {
+            let parent = renderer.create_node("div");
+            let child = renderer.create_node("button");
+            renderer.append(&renderer.root(), &parent);
+            renderer.append(&parent, &child);
+            let _listener = renderer.attach_listener(&child, "click", Box::new(|| {}));
+            renderer.remove(&parent);
+            removed.push(child);
+        }
+        assert_eq!(renderer.state.lock().unwrap().nodes.len(), 3);
+        // handles of removed nodes do not reach the nodes now in their slots
+        let node = renderer.create_node("p");
+        renderer.append(&renderer.root(), &node);
+        assert!(removed.iter().all(|child| renderer.tag(child).is_none()));
+        renderer.append(&renderer.root(), &removed[0]);
+        assert_eq!(renderer.children(&renderer.root()), vec![node]);
+    }
+}
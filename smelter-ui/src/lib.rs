@@ -1,5 +1,3 @@
-use std::{borrow::{Borrow, BorrowMut}, marker::PhantomData, sync::{Arc, Mutex}, thread::current};
-
 mod element;
 pub use crate::element::*;
 
@@ -12,9 +10,27 @@ pub use crate::proto::*;
 mod builder;
 pub use crate::builder::*;
 
+mod renderer;
+pub use crate::renderer::*;
+
+mod headless;
+pub use crate::headless::*;
+
 mod manipulation;
 pub use crate::manipulation::*; 
 
+mod render;
+
+#[cfg(feature = "web")]
+mod web;
+#[cfg(feature = "web")]
+pub use crate::web::*;
+
+#[cfg(feature = "web")]
+mod scheduler;
+#[cfg(feature = "web")]
+pub use crate::scheduler::*;
+
 
 #[cfg(test)]
 mod tests {
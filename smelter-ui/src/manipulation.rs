@@ -1,138 +1,587 @@
+use std::{borrow::BorrowMut, sync::{Arc, Mutex}};
 
-use std::{borrow::BorrowMut, sync::Arc};
+use crate::{DOMDocument, DOMElement, DOMElementType, Renderer};
+use crate::render::{is_attribute_name, is_property_name};
+use smelter_reflux::{AnyCancellable, Publish, Shared, Subscriber};
 
-use crate::{DOMDocument, DOMElement, DOMElementState, DOMElementType, element};
-use smelter_reflux::Publish;
-use wasm_bindgen::{JsCast, prelude::*};
-use web_sys::{Document, HtmlElement};
+#[cfg(feature = "web")]
+use crate::WebRenderer;
 
+#[cfg(feature = "web")]
 impl DOMDocument {
-    pub fn instantiate(self: &Arc<Self>) -> Arc<DOMDocumentInstance> {
-        let window = web_sys::window()
-            .expect("no global `window` exists");
-        let document = window.document()
-            .expect("should have a document on window");
-        let body = document.body()
+    pub fn instantiate(self: &Arc<Self>) -> Arc<DOMDocumentInstance<WebRenderer>> {
+        let body = web_document().body()
             .expect("document should have a body");
-        DOMDocumentInstance::new(self, &document, &body)
+        self.instantiate_into(&body)
+    }
+
+    // appends the elements to `container` after its existing children
+    pub fn instantiate_into(self: &Arc<Self>, container: &web_sys::Element) -> Arc<DOMDocumentInstance<WebRenderer>> {
+        let document = container.owner_document()
+            .expect("should have an owner document");
+        let renderer = Arc::new(WebRenderer::new(&document));
+        self.instantiate_with(&renderer, container)
+    }
+
+    // `None` when no element matches the selector
+    pub fn instantiate_into_selector(self: &Arc<Self>, selector: &str) -> Option<Arc<DOMDocumentInstance<WebRenderer>>> {
+        let container = web_document().query_selector(selector)
+            .ok()
+            .flatten()?;
+        Some(self.instantiate_into(&container))
+    }
+
+    // attaches to markup already under `root`, such as the output of `render_to_string`
+    pub fn hydrate(self: &Arc<Self>, root: &web_sys::Element) -> Arc<DOMDocumentInstance<WebRenderer>> {
+        let document = root.owner_document()
+            .expect("should have an owner document");
+        let renderer = Arc::new(WebRenderer::new(&document));
+        self.hydrate_with(&renderer, root)
+    }
+}
+
+#[cfg(feature = "web")]
+fn web_document() -> web_sys::Document {
+    let window = web_sys::window()
+        .expect("no global `window` exists");
+    window.document()
+        .expect("should have a document on window")
+}
+
+impl DOMDocument {
+    // creates the nodes under `root`, which is left in place
+    pub fn instantiate_with<R>(self: &Arc<Self>, renderer: &Arc<R>, root: &R::Node) -> Arc<DOMDocumentInstance<R>> where R: Renderer, R: 'static {
+        DOMDocumentInstance::new(self, renderer, root)
+    }
+
+    // binds to the nodes under `root` in the order instantiating would create them,
+    // parts that do not match are rebuilt and listed in `mismatches()`
+    pub fn hydrate_with<R>(self: &Arc<Self>, renderer: &Arc<R>, root: &R::Node) -> Arc<DOMDocumentInstance<R>> where R: Renderer, R: 'static {
+        DOMDocumentInstance::hydrate(self, renderer, root)
     }
 }
 
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HydrationMismatch {
+    // `path` holds the child indices leading to the node from the root
+    Missing { path: Vec<usize>, expected: String },
+    Tag { path: Vec<usize>, expected: String, actual: String },
+    Extra { path: Vec<usize>, actual: String },
+    Text { path: Vec<usize>, expected: String, actual: Option<String> },
+    Style { path: Vec<usize>, key: String, expected: String, actual: Option<String> },
+    Attribute { path: Vec<usize>, key: String, expected: String, actual: Option<String> },
+}
+
 impl DOMElement {
-    fn instantiate(self: &Arc<Self>, document: &web_sys::Document, element: &web_sys::Element) -> Arc<DOMElementInstance> {
+    fn instantiate<R>(self: &Arc<Self>, renderer: &Arc<R>, parent: &R::Node) -> Arc<DOMElementInstance<R>> where R: Renderer, R: 'static {
+        let children = if let Ok(mut guard) = self.state.lock() {
+            let state = guard.borrow_mut();
+            state.children.to_vec()
+        } else {
+            vec![]
+        };
+        DOMElementInstance::new(self, children, renderer, parent)
+    }
+
+    fn hydrate<R>(self: &Arc<Self>, renderer: &Arc<R>, node: &R::Node, path: &[usize], mismatches: &mut Vec<HydrationMismatch>) -> Arc<DOMElementInstance<R>> where R: Renderer, R: 'static {
         let children = if let Ok(mut guard) = self.state.lock() {
             let state = guard.borrow_mut();
-            state.children.iter()
-                .cloned()
-                .collect()
+            state.children.to_vec()
         } else {
             vec![]
         };
-        DOMElementInstance::new(self, children, document, element)
+        DOMElementInstance::hydrate(self, children, renderer, node, path, mismatches)
+    }
+}
+
+// everything from the first node of an unexpected type on is removed and created again
+fn hydrate_children<R>(
+    references: &[Arc<DOMElement>],
+    renderer: &Arc<R>,
+    parent: &R::Node,
+    path: &[usize],
+    mismatches: &mut Vec<HydrationMismatch>,
+) -> Vec<Arc<DOMElementInstance<R>>> where R: Renderer, R: 'static {
+    let existing = renderer.element_children(parent);
+    let mut diverged = false;
+    let mut instances = vec![];
+    for (index, reference) in references.iter().enumerate() {
+        let path = [path, &[index]].concat();
+        let expected = reference.element_type().name();
+        match existing.get(index) {
+            Some(node) if !diverged => {
+                let actual = renderer.tag_name(node);
+                if actual == expected {
+                    instances.push(reference.hydrate(renderer, node, &path, mismatches));
+                    continue;
+                }
+                mismatches.push(HydrationMismatch::Tag { path, expected, actual });
+                diverged = true;
+                for node in existing[index..].iter() {
+                    renderer.remove(node);
+                }
+            },
+            None if !diverged => mismatches.push(HydrationMismatch::Missing { path, expected }),
+            _ => {},
+        }
+        instances.push(reference.instantiate(renderer, parent));
+    }
+    if !diverged {
+        for (index, node) in existing.iter().enumerate().skip(references.len()) {
+            let path = [path, &[index]].concat();
+            mismatches.push(HydrationMismatch::Extra { path, actual: renderer.tag_name(node) });
+            renderer.remove(node);
+        }
     }
+    instances
 }
 
-pub struct DOMDocumentInstance {
-    reference_element: Arc<DOMDocument>,
-    elements: Vec<Arc<DOMElementInstance>>,
+// instances own the created nodes and their bindings until unmounted, at the latest when dropped
+pub struct DOMDocumentInstance<R> where R: Renderer {
+    renderer: Arc<R>,
+    elements: Mutex<Vec<Arc<DOMElementInstance<R>>>>,
+    mismatches: Vec<HydrationMismatch>,
 }
 
-impl DOMDocumentInstance {
-    fn new(reference: &Arc<DOMDocument>, document: &web_sys::Document, element: &web_sys::Element) -> Arc<Self> {
+impl<R> DOMDocumentInstance<R> where R: Renderer, R: 'static {
+    fn new(reference: &Arc<DOMDocument>, renderer: &Arc<R>, root: &R::Node) -> Arc<Self> {
         let elements: Vec<_> = reference.elements.iter()
-            .map(|v| v.instantiate(document, &element))
+            .map(|v| v.instantiate(renderer, root))
             .collect();
         let instance = Self {
-            reference_element: Arc::clone(reference),
-            elements,
+            renderer: Arc::clone(renderer),
+            elements: Mutex::new(elements),
+            mismatches: vec![],
         };
         Arc::new(instance)
     }
+
+    fn hydrate(reference: &Arc<DOMDocument>, renderer: &Arc<R>, root: &R::Node) -> Arc<Self> {
+        let mut mismatches = vec![];
+        let elements = hydrate_children(&reference.elements, renderer, root, &[], &mut mismatches);
+        let instance = Self {
+            renderer: Arc::clone(renderer),
+            elements: Mutex::new(elements),
+            mismatches,
+        };
+        Arc::new(instance)
+    }
+
+    // the differences found while hydrating, empty for instantiated documents
+    pub fn mismatches(&self) -> &[HydrationMismatch] {
+        &self.mismatches
+    }
+}
+
+impl<R> DOMDocumentInstance<R> where R: Renderer {
+    // removes the nodes from their container, then drops the listeners and text subscriptions.
+    // does nothing the second time
+    pub fn unmount(&self) {
+        let elements = self.elements.lock()
+            .map(|mut guard| std::mem::take(&mut *guard))
+            .unwrap_or_default();
+        for element in elements.iter() {
+            self.renderer.remove(&element.node);
+        }
+        drop(elements);
+    }
+}
+
+impl<R> Drop for DOMDocumentInstance<R> where R: Renderer {
+    fn drop(&mut self) {
+        self.unmount();
+    }
 }
 
-pub struct DOMElementInstance {
-    reference_element: Arc<DOMElement>,
-    reference_children: Vec<Arc<DOMElement>>,
-    element: web_sys::Element,
-    children: Vec<Arc<DOMElementInstance>>,
-    binding: DOMElementInstanceBinding,
+pub struct DOMElementInstance<R> where R: Renderer {
+    node: R::Node,
+    // held for their nodes and bindings, which go along with this instance
+    _children: Vec<Arc<DOMElementInstance<R>>>,
+    _binding: DOMElementInstanceBinding<R>,
 }
 
-impl DOMElementInstance {
+impl<R> DOMElementInstance<R> where R: Renderer, R: 'static {
     fn new(
         reference: &Arc<DOMElement>,
         reference_children: Vec<Arc<DOMElement>>,
-        document: &web_sys::Document,
-        parent: &web_sys::Element,
+        renderer: &Arc<R>,
+        parent: &R::Node,
     ) -> Arc<Self> {
         let element_type = reference.element_type();
         let element_name = element_type.name();
-        let element = document.create_element(element_name.as_str()).unwrap();
-        parent.append_child(&element);
+        let node = renderer.create_node(element_name.as_str());
+        renderer.append(parent, &node);
         let children: Vec<_> = reference_children.iter()
-            .map(|child| child.instantiate(document, &element))
+            .map(|child| child.instantiate(renderer, &node))
             .collect();
-        let html_element = element
-            .dyn_ref::<HtmlElement>()
-            .unwrap();
-        let binding = DOMElementInstanceBinding::new(reference, html_element);
+        let binding = DOMElementInstanceBinding::new(reference, renderer, &node, false);
+        let instance = Self {
+            node,
+            _children: children,
+            _binding: binding,
+        };
+        Arc::new(instance)
+    }
+
+    fn hydrate(
+        reference: &Arc<DOMElement>,
+        reference_children: Vec<Arc<DOMElement>>,
+        renderer: &Arc<R>,
+        node: &R::Node,
+        path: &[usize],
+        mismatches: &mut Vec<HydrationMismatch>,
+    ) -> Arc<Self> {
+        hydrate_properties(reference, renderer, node, path, mismatches);
+        let text = reference.text_subject()
+            .and_then(|v| v.value());
+        // a text replaces the children when instantiating, so the markup has none of them
+        let children = match text {
+            Some(expected) => {
+                let actual = renderer.text_content(node);
+                if actual.as_ref() != Some(&expected) {
+                    renderer.set_text(node, Some(expected.as_str()));
+                    mismatches.push(HydrationMismatch::Text { path: path.to_vec(), expected, actual });
+                }
+                vec![]
+            },
+            None => hydrate_children(&reference_children, renderer, node, path, mismatches),
+        };
+        let binding = DOMElementInstanceBinding::new(reference, renderer, node, true);
         let instance = Self {
-            reference_element: Arc::clone(reference),
-            reference_children,
-            element,
-            children,
-            binding,
+            node: node.clone(),
+            _children: children,
+            _binding: binding,
         };
-        let instance = Arc::new(instance);
-        instance
+        Arc::new(instance)
     }
 }
 
-impl DOMElementType {
-    fn name(&self) -> String {
-        match &self {
-            &DOMElementType::Div => "div".into(),
-            &DOMElementType::Button => "button".into(),
-            &DOMElementType::Paragraph => "p".into(),
+// styles and attributes the markup lacks or holds other values for are set again,
+// names rendering leaves out are not expected in the markup
+fn hydrate_properties<R>(reference: &Arc<DOMElement>, renderer: &Arc<R>, node: &R::Node, path: &[usize], mismatches: &mut Vec<HydrationMismatch>) where R: Renderer {
+    let styles = reference.styles()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|(key, _)| is_property_name(key));
+    for (key, expected) in styles {
+        let actual = renderer.get_style(node, &key);
+        if actual.as_ref() != Some(&expected) {
+            renderer.set_style(node, &key, &expected);
+            mismatches.push(HydrationMismatch::Style { path: path.to_vec(), key, expected, actual });
+        }
+    }
+    let attributes = reference.attributes()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|(key, _)| is_attribute_name(key));
+    for (key, expected) in attributes {
+        let actual = renderer.get_attribute(node, &key);
+        if actual.as_ref() != Some(&expected) {
+            renderer.set_attribute(node, &key, &expected);
+            mismatches.push(HydrationMismatch::Attribute { path: path.to_vec(), key, expected, actual });
         }
     }
 }
 
-struct DOMElementInstanceBinding {
+impl DOMElementType {
+    pub(crate) fn name(&self) -> String {
+        match self {
+            DOMElementType::Div => "div".into(),
+            DOMElementType::Button => "button".into(),
+            DOMElementType::Paragraph => "p".into(),
+        }
+    }
+}
 
+// detaches the listeners and text subscriptions once dropped
+struct DOMElementInstanceBinding<R> where R: Renderer {
+    _cancellables: Vec<AnyCancellable>,
+    _listeners: Vec<R::Listener>,
 }
 
-impl DOMElementInstanceBinding {
-    fn new(reference: &Arc<DOMElement>, element: &web_sys::HtmlElement) -> Self {
+impl<R> DOMElementInstanceBinding<R> where R: Renderer, R: 'static {
+    // hydrated nodes already carry their styles, attributes and text
+    fn new(reference: &Arc<DOMElement>, renderer: &Arc<R>, node: &R::Node, hydrated: bool) -> Self {
+        let mut cancellables = vec![];
+        let mut listeners = vec![];
         // styles
-        if let Some(styles) = reference.styles() {
+        if let Some(styles) = reference.styles().filter(|_| !hydrated) {
             for (key, value) in styles.iter() {
-                element.style().set_property(key.as_str(), value.as_str());
+                renderer.set_style(node, key.as_str(), value.as_str());
+            }
+        }
+        // attributes
+        if let Some(attributes) = reference.attributes().filter(|_| !hydrated) {
+            for (key, value) in attributes.iter() {
+                renderer.set_attribute(node, key.as_str(), value.as_str());
             }
         }
         // onclick
         if let Some(onclick_publisher) = reference.onclick_publisher() {
-            let onclick = Closure::wrap(Box::new(move || {
-                // web_sys::console::log_1(&JsValue::from(format!("clicked")));
+            let listener = renderer.attach_listener(node, "click", Box::new(move || {
                 onclick_publisher.send_value(&());
-            }) as Box<dyn FnMut()>);
-            element.set_onclick(Some(onclick.as_ref().unchecked_ref()));
-            onclick.forget();
+            }));
+            listeners.push(listener);
         }
-        // text_property
-        if let Some(text_property) = reference.text_property() {
-            let text = text_property.value().flatten();
+        // text_subject
+        if let Some(text_subject) = reference.text_subject() {
+            let text = text_subject.value().filter(|_| !hydrated);
             if let Some(text) = text {
-                element.set_text_content(Some(text.as_str()));
+                renderer.set_text(node, Some(text.as_str()));
             }
-            let element = element.clone();
-            text_property.subscriber()
+            let renderer = Arc::clone(renderer);
+            let node = node.clone();
+            let subscriber: Shared<Subscriber<Option<String>>> = Subscriber::new();
+            // the replayed current value is already applied, and an empty one would drop the children
+            subscriber.skip(1)
                 .sink(move |v| {
-                    element.set_text_content(v.as_ref().map(|v| v.as_str()));
-                });
+                    renderer.set_text(&node, v.as_ref().map(|v| v.as_str()));
+                })
+                .store_in(&mut cancellables);
+            text_subject.receive_subscriber(&subscriber)
+                .store_in(&mut cancellables);
         }
-        let binding = Self {
-        };
-        binding
+        Self {
+            _cancellables: cancellables,
+            _listeners: listeners,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use crate::*;
+    use smelter_reflux::*;
+
+    // records the operations, nodes are numbered in creation order from the root 0
+    struct RecordingRenderer {
+        operations: Mutex<Vec<String>>,
+        listeners: Mutex<Vec<Box<dyn Fn()>>>,
+    }
+
+    impl Renderer for RecordingRenderer {
+        type Node = usize;
+        type Listener = ();
+
+        fn create_node(&self, name: &str) -> usize {
+            let mut operations = self.operations.lock().unwrap();
+            let id = 1 + operations.iter().filter(|v| v.starts_with("create")).count();
+            operations.push(format!("create {} {}", id, name));
+            id
+        }
+
+        fn append(&self, parent: &usize, child: &usize) {
+            self.operations.lock().unwrap().push(format!("append {} {}", parent, child));
+        }
+
+        fn set_text(&self, node: &usize, text: Option<&str>) {
+            self.operations.lock().unwrap().push(format!("text {} {:?}", node, text));
+        }
+
+        fn set_style(&self, node: &usize, key: &str, value: &str) {
+            self.operations.lock().unwrap().push(format!("style {} {}: {}", node, key, value));
+        }
+
+        fn set_attribute(&self, node: &usize, key: &str, value: &str) {
+            self.operations.lock().unwrap().push(format!("attribute {} {}={}", node, key, value));
+        }
+
+        fn attach_listener(&self, node: &usize, event: &str, listener: Box<dyn Fn()>) {
+            self.operations.lock().unwrap().push(format!("listen {} {}", node, event));
+            self.listeners.lock().unwrap().push(listener);
+        }
+
+        fn remove(&self, node: &usize) {
+            self.operations.lock().unwrap().push(format!("remove {}", node));
+        }
+
+        fn element_children(&self, _node: &usize) -> Vec<usize> {
+            vec![]
+        }
+
+        fn tag_name(&self, _node: &usize) -> String {
+            String::new()
+        }
+
+        fn text_content(&self, _node: &usize) -> Option<String> {
+            None
+        }
+
+        fn get_style(&self, _node: &usize, _key: &str) -> Option<String> {
+            None
+        }
+
+        fn get_attribute(&self, _node: &usize, _key: &str) -> Option<String> {
+            None
+        }
+    }
+
+    #[test]
+    #[allow(clippy::arc_with_non_send_sync)]
+    fn instantiate_with() {
+        let mut builder = DocumentBuilder::new();
+        let context = &mut builder;
+        let text_content = Publisher::new();
+        let onclick = Subscriber::new();
+        let _binding = onclick
+            .map(|_| Some("Clicked".to_string()))
+            .bind(&text_content);
+        Division::new(context)
+            .attribute("id", "app")
+            .children(|context| {
+                Button::new(context)
+                    .text("Hello")
+                    .style("font-size", "12pt")
+                    .publish_onclick(&onclick)
+                    .subscribe_text(&text_content);
+            });
+        let document = builder.build();
+        let renderer = Arc::new(RecordingRenderer {
+            operations: Mutex::new(vec![]),
+            listeners: Mutex::new(vec![]),
+        });
+        let _instance = document.instantiate_with(&renderer, &0);
+        // the button is clicked through the listener it registered
+        (renderer.listeners.lock().unwrap()[0])();
+        assert_eq!(*renderer.operations.lock().unwrap(), vec![
+            "create 1 div",
+            "append 0 1",
+            "create 2 button",
+            "append 1 2",
+            "style 2 font-size: 12pt",
+            "listen 2 click",
+            "text 2 Some(\"Hello\")",
+            "attribute 1 id=app",
+            "listen 1 click",
+            "text 2 Some(\"Clicked\")",
+        ]);
+    }
+
+    // stands in for server-rendered markup, each node goes under the last one `depth` levels down
+    fn markup(renderer: &HeadlessRenderer, parent: &HeadlessNode, elements: &[(&str, Option<&str>, usize)]) -> Vec<HeadlessNode> {
+        elements.iter()
+            .map(|(name, text, depth)| {
+                let node = renderer.create_node(name);
+                let parent = (0..*depth).fold(*parent, |parent, _| *renderer.children(&parent).last().unwrap());
+                renderer.append(&parent, &node);
+                renderer.set_text(&node, *text);
+                node
+            })
+            .collect()
+    }
+
+    fn document(text_content: &Shared<Publisher<Option<String>>>, onclick: &Shared<Subscriber<()>>) -> Arc<DOMDocument> {
+        let mut builder = DocumentBuilder::new();
+        let context = &mut builder;
+        Division::new(context).children(|context| {
+            Paragraph::new(context)
+                .text("Smelter")
+                .subscribe_text(text_content);
+            Button::new(context)
+                .text("Hello")
+                .publish_onclick(onclick);
+        });
+        builder.build()
+    }
+
+    #[test]
+    #[allow(clippy::arc_with_non_send_sync)]
+    fn hydrate() {
+        let text_content = Publisher::new();
+        let onclick = Subscriber::new();
+        let _binding = onclick
+            .map(|_| Some("Clicked".to_string()))
+            .bind(&text_content);
+        let document = document(&text_content, &onclick);
+        let renderer = Arc::new(HeadlessRenderer::new());
+        let nodes = markup(&renderer, &renderer.root(), &[("div", None, 0), ("p", Some("Smelter"), 1), ("button", Some("Hello"), 1)]);
+        let instance = document.hydrate_with(&renderer, &renderer.root());
+        assert!(instance.mismatches().is_empty());
+        // the existing nodes are bound rather than created again
+        assert_eq!(renderer.find_by_tag("p"), vec![nodes[1]]);
+        renderer.click(&nodes[2]);
+        assert_eq!(renderer.text(&nodes[1]).as_deref(), Some("Clicked"));
+    }
+
+    #[test]
+    #[allow(clippy::arc_with_non_send_sync)]
+    fn hydrate_mismatches() {
+        let text_content = Publisher::new();
+        let onclick = Subscriber::new();
+        let document = document(&text_content, &onclick);
+        let renderer = Arc::new(HeadlessRenderer::new());
+        markup(&renderer, &renderer.root(), &[("div", None, 0), ("p", Some("Stale"), 1), ("p", None, 1), ("div", None, 0)]);
+        let instance = document.hydrate_with(&renderer, &renderer.root());
+        assert_eq!(instance.mismatches(), &[
+            HydrationMismatch::Text { path: vec![0, 0], expected: "Smelter".into(), actual: Some("Stale".into()) },
+            HydrationMismatch::Tag { path: vec![0, 1], expected: "button".into(), actual: "p".into() },
+            HydrationMismatch::Extra { path: vec![1], actual: "div".into() },
+        ]);
+        assert_eq!(renderer.snapshot(&renderer.root()), concat!(
+            "<body>\n",
+            "  <div>\n",
+            "    <p>Smelter</p>\n",
+            "    <button>Hello</button>\n",
+            "  </div>\n",
+            "</body>\n",
+        ));
+    }
+
+    #[test]
+    #[allow(clippy::arc_with_non_send_sync)]
+    fn hydrate_property_mismatches() {
+        let mut builder = DocumentBuilder::new();
+        let context = &mut builder;
+        Paragraph::new(context)
+            .style("color", "red")
+            .style("font-size", "12pt")
+            .attribute("id", "title")
+            .attribute("title", "Smelter");
+        let document = builder.build();
+        let renderer = Arc::new(HeadlessRenderer::new());
+        let nodes = markup(&renderer, &renderer.root(), &[("p", None, 0)]);
+        renderer.set_style(&nodes[0], "color", "red");
+        renderer.set_style(&nodes[0], "font-size", "10pt");
+        renderer.set_attribute(&nodes[0], "id", "title");
+        let instance = document.hydrate_with(&renderer, &renderer.root());
+        assert_eq!(instance.mismatches(), &[
+            HydrationMismatch::Style { path: vec![0], key: "font-size".into(), expected: "12pt".into(), actual: Some("10pt".into()) },
+            HydrationMismatch::Attribute { path: vec![0], key: "title".into(), expected: "Smelter".into(), actual: None },
+        ]);
+        assert_eq!(renderer.snapshot(&nodes[0]), "<p id=\"title\" title=\"Smelter\" style=\"color: red; font-size: 12pt\"></p>\n");
+    }
+
+    #[test]
+    #[allow(clippy::arc_with_non_send_sync)]
+    fn unmount() {
+        let text_content = Publisher::new();
+        let onclick = Subscriber::new();
+        let clicks = Arc::new(Mutex::new(0));
+        let clicks_ref = Arc::clone(&clicks);
+        let _sink = onclick.sink(move |_| *clicks_ref.lock().unwrap() += 1);
+        let document = document(&text_content, &onclick);
+        let renderer = Arc::new(HeadlessRenderer::new());
+        let container = renderer.create_node("div");
+        renderer.append(&renderer.root(), &container);
+        let instance = document.instantiate_with(&renderer, &container);
+        let paragraph = renderer.find_by_tag("p")[0];
+        let button = renderer.find_by_tag("button")[0];
+        renderer.click(&button);
+        instance.unmount();
+        assert_eq!(renderer.snapshot(&container), "<div></div>\n");
+        // the removed nodes are freed and no longer react
+        renderer.click(&button);
+        text_content.send_value(&Some("Updated".into()));
+        assert_eq!(*clicks.lock().unwrap(), 1);
+        assert_eq!(renderer.tag(&paragraph), None);
+        instance.unmount();
+
+        // dropping unmounts as well
+        let instance = document.instantiate_with(&renderer, &container);
+        assert_eq!(renderer.find_by_tag("button").len(), 1);
+        drop(instance);
+        assert!(renderer.find_by_tag("button").is_empty());
     }
 }
@@ -1,7 +1,5 @@
 use std::sync::Arc;
 
-use smelter_reflux::{Publish, Publisher, Subscriber};
-
 use crate::{DOMContext, DOMElement, DOMElementType, DeclareElement};
 
 
@@ -13,7 +11,7 @@ pub struct Button<'a, Ctx> {
 impl<'a, Ctx> Button<'a, Ctx> where Ctx: DOMContext {
     pub fn new(context: &'a mut Ctx) -> Self {
         let element = context.create_element(DOMElementType::Button);
-        Self { context: context, element: element, }
+        Self { context, element, }
     }
 }
 
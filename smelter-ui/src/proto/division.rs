@@ -10,7 +10,7 @@ pub struct Division<'a, Ctx> {
 impl<'a, Ctx> Division<'a, Ctx> where Ctx: DOMContext {
     pub fn new(context: &'a mut Ctx) -> Self {
         let element = context.create_element(DOMElementType::Div);
-        Self { context: context, element: element, }
+        Self { context, element, }
     }
 }
 
@@ -1,9 +1,9 @@
 
-use std::{borrow::BorrowMut, sync::{Arc, Mutex}};
+use std::sync::Arc;
 
-use smelter_reflux::{Publisher, Subscriber, Publish};
+use smelter_reflux::{AnyCancellable, Publisher, Subscriber, Publish, Shared};
 
-use crate::{DOMElement, DOMContext, DOMElementType};
+use crate::{DOMElement, DOMContext};
 
 pub trait DeclareElement {
     type Context;
@@ -20,14 +20,22 @@ pub trait DeclareTraverse {
 
 pub trait DeclareTextManipulate {
     fn text<S>(self, text: S) -> Self where S: Into<String>;
-    fn publish_onclick(self, subscriber: &Arc<Subscriber<()>>) -> Self;
-    fn subscribe_text(self, publisher: &Arc<Publisher<Option<String>>>) -> Self;
+    fn publish_onclick(self, subscriber: &Shared<Subscriber<()>>) -> Self;
+    fn subscribe_text(self, publisher: &Shared<Publisher<Option<String>>>) -> Self;
 }
 
 pub trait DeclareStyleManipulate {
     fn style<S0, S1>(self, key: S0, value: S1) -> Self where S0: Into<String>, S1: Into<String>;
 }
 
+pub trait DeclareAttributeManipulate {
+    fn attribute<S0, S1>(self, key: S0, value: S1) -> Self where S0: Into<String>, S1: Into<String>;
+}
+
+pub trait DeclareCancellableManipulate {
+    fn retain<I>(self, cancellables: I) -> Self where I: IntoIterator<Item = AnyCancellable>;
+}
+
 impl<T, Ctx> DeclareTraverse for T where T: DeclareElement<Context = Ctx>, Ctx: DOMContext {
     type Context = Ctx;
 
@@ -44,24 +52,27 @@ impl<T, Ctx> DeclareTraverse for T where T: DeclareElement<Context = Ctx>, Ctx:
 impl<T, Ctx> DeclareTextManipulate for T where T: DeclareElement<Context = Ctx>, Ctx: DOMContext {
     fn text<S>(self, text: S) -> Self where S: Into<String> {
         let element = self.element();
-        if let Some(text_property) = element.text_property() {
-            text_property.accept(&Some(text.into()));
+        if let Some(text_subject) = element.text_subject() {
+            text_subject.send(&Some(text.into()));
         }
         self
     }
 
-    fn publish_onclick(self, subscriber: &Arc<Subscriber<()>>) -> Self {
+    fn publish_onclick(self, subscriber: &Shared<Subscriber<()>>) -> Self {
         let element = self.element();
         if let Some(onclick_publisher) = element.onclick_publisher() {
-            onclick_publisher.receive_subscriber(subscriber);
+            element.push_cancellable(onclick_publisher.receive_subscriber(subscriber));
         }
         self
     }
 
-    fn subscribe_text(self, publisher: &Arc<Publisher<Option<String>>>) -> Self {
+    fn subscribe_text(self, publisher: &Shared<Publisher<Option<String>>>) -> Self {
         let element = self.element();
-        if let Some(text_property) = element.text_property() {
-            publisher.receive_subscriber(text_property.subscriber())
+        if let Some(text_subject) = element.text_subject() {
+            // only values are forwarded, the text stays once the publisher completes
+            let subscriber = Subscriber::new();
+            element.push_cancellable(subscriber.bind(text_subject.publisher()));
+            element.push_cancellable(publisher.receive_subscriber(&subscriber));
         }
         self
     }
@@ -75,6 +86,24 @@ impl<T, Ctx> DeclareStyleManipulate for T where T: DeclareElement<Context = Ctx>
     }
 }
 
+impl<T, Ctx> DeclareAttributeManipulate for T where T: DeclareElement<Context = Ctx>, Ctx: DOMContext {
+    fn attribute<S0, S1>(self, key: S0, value: S1) -> Self where S0: Into<String>, S1: Into<String> {
+        let element = self.element();
+        element.push_attribute(key.into(), value.into());
+        self
+    }
+}
+
+impl<T, Ctx> DeclareCancellableManipulate for T where T: DeclareElement<Context = Ctx>, Ctx: DOMContext {
+    fn retain<I>(self, cancellables: I) -> Self where I: IntoIterator<Item = AnyCancellable> {
+        let element = self.element();
+        for cancellable in cancellables {
+            element.push_cancellable(cancellable);
+        }
+        self
+    }
+}
+
 mod division;
 pub use crate::proto::division::*;
 
@@ -10,7 +10,7 @@ pub struct Paragraph<'a, Ctx> {
 impl<'a, Ctx> Paragraph<'a, Ctx> where Ctx: DOMContext {
     pub fn new(context: &'a mut Ctx) -> Self {
         let element = context.create_element(DOMElementType::Paragraph);
-        Self { context: context, element: element, }
+        Self { context, element, }
     }
 }
 
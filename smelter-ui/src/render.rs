@@ -0,0 +1,179 @@
+use std::{io::{self, Write}, sync::Arc};
+
+use crate::{DOMDocument, DOMElement};
+
+impl DOMDocument {
+    // the markup instantiating the document would produce, without whitespace between elements
+    pub fn render_to_string(&self) -> String {
+        let mut buffer = vec![];
+        // writing to a vector never fails and only valid utf-8 is written
+        let _ = self.render_to_writer(&mut buffer);
+        String::from_utf8(buffer)
+            .unwrap_or_default()
+    }
+
+    pub fn render_to_writer<W>(&self, mut writer: W) -> io::Result<()> where W: Write {
+        for element in self.elements.iter() {
+            element.render(&mut writer)?;
+        }
+        writer.flush()
+    }
+}
+
+impl DOMElement {
+    fn render<W>(self: &Arc<Self>, writer: &mut W) -> io::Result<()> where W: Write {
+        let name = self.element_type().name();
+        write!(writer, "<{}", name)?;
+        // a name outside the grammar could close the tag or add attributes, it is left out
+        let attributes = self.attributes()
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|(key, _)| is_attribute_name(key));
+        for (key, value) in attributes {
+            write!(writer, " {}=\"", key)?;
+            escape(writer, &value)?;
+            write!(writer, "\"")?;
+        }
+        let styles: Vec<(String, String)> = self.styles()
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|(key, _)| is_property_name(key))
+            .collect();
+        if !styles.is_empty() {
+            write!(writer, " style=\"")?;
+            for (index, (key, value)) in styles.iter().enumerate() {
+                if index > 0 {
+                    write!(writer, " ")?;
+                }
+                escape(writer, &format!("{}: {};", key, value))?;
+            }
+            write!(writer, "\"")?;
+        }
+        write!(writer, ">")?;
+        // like instantiating, the text replaces the children
+        let text = self.text_subject()
+            .and_then(|v| v.value());
+        if let Some(text) = text {
+            escape(writer, &text)?;
+        } else {
+            let children = self.state.lock()
+                .map(|guard| guard.children.to_vec())
+                .unwrap_or_default();
+            for child in children.iter() {
+                child.render(writer)?;
+            }
+        }
+        write!(writer, "</{}>", name)
+    }
+}
+
+// any character but controls, whitespace, quotes, `<`, `>`, `/`, `=` and noncharacters
+pub(crate) fn is_attribute_name(name: &str) -> bool {
+    let is_noncharacter = |c: char| matches!(c as u32, 0xFDD0..=0xFDEF) || (c as u32) & 0xFFFE == 0xFFFE;
+    !name.is_empty() && !name.chars().any(|c| {
+        c.is_control() || c.is_whitespace() || matches!(c, '"' | '\'' | '<' | '>' | '/' | '=') || is_noncharacter(c)
+    })
+}
+
+// a css identifier such as `font-size`, `-webkit-user-select` or a custom `--accent`
+pub(crate) fn is_property_name(name: &str) -> bool {
+    let rest = name.strip_prefix("--")
+        .or_else(|| name.strip_prefix('-'))
+        .unwrap_or(name);
+    let custom = name.starts_with("--");
+    let mut chars = rest.chars();
+    let starts = match chars.next() {
+        Some(c) => c.is_ascii_alphabetic() || c == '_' || (custom && (c.is_ascii_digit() || c == '-')),
+        None => false,
+    };
+    starts && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+// safe both as text and inside quoted attribute values
+fn escape<W>(writer: &mut W, text: &str) -> io::Result<()> where W: Write {
+    let bytes = text.as_bytes();
+    let mut start = 0;
+    for (index, c) in text.char_indices() {
+        let escaped = match c {
+            '&' => "&amp;",
+            '<' => "&lt;",
+            '>' => "&gt;",
+            '"' => "&quot;",
+            '\'' => "&#39;",
+            _ => continue,
+        };
+        writer.write_all(&bytes[start..index])?;
+        writer.write_all(escaped.as_bytes())?;
+        start = index + c.len_utf8();
+    }
+    writer.write_all(&bytes[start..])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use smelter_reflux::*;
+
+    #[test]
+    fn render_to_string() {
+        let mut builder = DocumentBuilder::new();
+        let context = &mut builder;
+        let text_content = Publisher::new();
+        Division::new(context).children(|context| {
+            Paragraph::new(context)
+                .text("Smelter")
+                .style("font-size", "32pt")
+                .style("color", "red");
+            Division::new(context)
+                .attribute("id", "app")
+                .children(|context| {
+                    Button::new(context)
+                        .text("Hello world!")
+                        .subscribe_text(&text_content);
+                });
+        });
+        let document = builder.build();
+        assert_eq!(document.render_to_string(), concat!(
+            "<div>",
+            "<p style=\"font-size: 32pt; color: red;\">Smelter</p>",
+            "<div id=\"app\"><button>Hello world!</button></div>",
+            "</div>",
+        ));
+        // the current text is rendered
+        text_content.send_value(&Some("Clicked".into()));
+        assert!(document.render_to_string().contains("<button>Clicked</button>"));
+    }
+
+    #[test]
+    fn escape() {
+        let mut builder = DocumentBuilder::new();
+        let context = &mut builder;
+        Paragraph::new(context)
+            .text("<script>alert('x') & \"y\"</script>")
+            .attribute("title", "\"><img>")
+            .style("font-family", "\"Noto Sans\"");
+        let document = builder.build();
+        let mut rendered = vec![];
+        document.render_to_writer(&mut rendered).unwrap();
+        assert_eq!(String::from_utf8(rendered).unwrap(), concat!(
+            "<p title=\"&quot;&gt;&lt;img&gt;\" style=\"font-family: &quot;Noto Sans&quot;;\">",
+            "&lt;script&gt;alert(&#39;x&#39;) &amp; &quot;y&quot;&lt;/script&gt;",
+            "</p>",
+        ));
+    }
+
+    #[test]
+    fn invalid_names() {
+        let mut builder = DocumentBuilder::new();
+        let context = &mut builder;
+        Paragraph::new(context)
+            .attribute("x onload=alert(1)", "")
+            .attribute("\"><script>", "")
+            .attribute("data-x", "1")
+            .style("color: red; background", "url(x)")
+            .style("--accent", "blue")
+            .style("margin-top", "1px");
+        let document = builder.build();
+        assert_eq!(document.render_to_string(), "<p data-x=\"1\" style=\"--accent: blue; margin-top: 1px;\"></p>");
+    }
+}
@@ -0,0 +1,24 @@
+// the node operations instantiating a document needs, implemented by each backend
+pub trait Renderer {
+    type Node: Clone;
+    // detaches the listener once dropped
+    type Listener;
+
+    fn create_node(&self, name: &str) -> Self::Node;
+    fn append(&self, parent: &Self::Node, child: &Self::Node);
+    // `None` clears the text along with any children
+    fn set_text(&self, node: &Self::Node, text: Option<&str>);
+    fn set_style(&self, node: &Self::Node, key: &str, value: &str);
+    fn set_attribute(&self, node: &Self::Node, key: &str, value: &str);
+    fn attach_listener(&self, node: &Self::Node, event: &str, listener: Box<dyn Fn()>) -> Self::Listener;
+    // detaches the node from its parent for good, it is not used again afterwards
+    fn remove(&self, node: &Self::Node);
+
+    // reading existing nodes, for hydrating markup rendered elsewhere
+    fn element_children(&self, node: &Self::Node) -> Vec<Self::Node>;
+    // in lowercase
+    fn tag_name(&self, node: &Self::Node) -> String;
+    fn text_content(&self, node: &Self::Node) -> Option<String>;
+    fn get_style(&self, node: &Self::Node, key: &str) -> Option<String>;
+    fn get_attribute(&self, node: &Self::Node, key: &str) -> Option<String>;
+}
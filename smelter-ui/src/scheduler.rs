@@ -0,0 +1,54 @@
+use std::time::Duration;
+
+use smelter_reflux::{Action, AnyCancellable, Scheduler, Shared};
+use wasm_bindgen::{JsCast, prelude::*};
+
+// runs actions from the browser event loop through `setTimeout`
+pub struct WebScheduler {
+    window: web_sys::Window,
+}
+
+impl WebScheduler {
+    pub fn new() -> Shared<Self> {
+        let window = web_sys::window()
+            .expect("no global `window` exists");
+        let scheduler = Self {
+            window,
+        };
+        Shared::new(scheduler)
+    }
+
+    fn set_timeout(&self, delay: Duration, callback: &JsValue) -> Option<i32> {
+        let timeout = i32::try_from(delay.as_millis()).unwrap_or(i32::MAX);
+        self.window.set_timeout_with_callback_and_timeout_and_arguments_0(callback.unchecked_ref(), timeout)
+            .ok()
+    }
+}
+
+impl Scheduler for WebScheduler {
+    fn now(&self) -> Duration {
+        let millis = self.window.performance()
+            .map(|v| v.now())
+            .unwrap_or_default();
+        Duration::from_secs_f64(millis / 1000.0)
+    }
+
+    fn schedule(&self, action: Action) {
+        // the closure frees itself once called
+        let callback = Closure::once_into_js(action);
+        self.set_timeout(Duration::ZERO, &callback);
+    }
+
+    fn schedule_after(&self, delay: Duration, action: Action) -> AnyCancellable {
+        // the handle keeps the closure alive until the timer is cleared
+        let closure = Closure::once(action);
+        let handle = self.set_timeout(delay, closure.as_ref());
+        let window = self.window.clone();
+        AnyCancellable::new(move || {
+            if let Some(handle) = handle {
+                window.clear_timeout_with_handle(handle);
+            }
+            drop(closure);
+        })
+    }
+}
@@ -0,0 +1,101 @@
+use wasm_bindgen::{JsCast, prelude::*};
+use web_sys::HtmlElement;
+
+use crate::Renderer;
+
+// renders into the browser document through web-sys
+pub struct WebRenderer {
+    document: web_sys::Document,
+}
+
+impl WebRenderer {
+    pub fn new(document: &web_sys::Document) -> Self {
+        Self {
+            document: document.clone(),
+        }
+    }
+}
+
+// keeps the closure alive while it is registered on the element
+pub struct WebListener {
+    element: web_sys::Element,
+    event: String,
+    closure: Closure<dyn Fn()>,
+}
+
+impl Drop for WebListener {
+    fn drop(&mut self) {
+        let _ = self.element.remove_event_listener_with_callback(self.event.as_str(), self.closure.as_ref().unchecked_ref());
+    }
+}
+
+impl Renderer for WebRenderer {
+    type Node = web_sys::Element;
+    type Listener = WebListener;
+
+    fn create_node(&self, name: &str) -> Self::Node {
+        self.document.create_element(name)
+            .expect("should be a valid element name")
+    }
+
+    fn append(&self, parent: &Self::Node, child: &Self::Node) {
+        let _ = parent.append_child(child);
+    }
+
+    fn set_text(&self, node: &Self::Node, text: Option<&str>) {
+        node.set_text_content(text);
+    }
+
+    fn set_style(&self, node: &Self::Node, key: &str, value: &str) {
+        if let Some(element) = node.dyn_ref::<HtmlElement>() {
+            let _ = element.style().set_property(key, value);
+        }
+    }
+
+    fn set_attribute(&self, node: &Self::Node, key: &str, value: &str) {
+        let _ = node.set_attribute(key, value);
+    }
+
+    fn attach_listener(&self, node: &Self::Node, event: &str, listener: Box<dyn Fn()>) -> Self::Listener {
+        let closure = Closure::wrap(listener);
+        let _ = node.add_event_listener_with_callback(event, closure.as_ref().unchecked_ref());
+        WebListener {
+            element: node.clone(),
+            event: event.into(),
+            closure,
+        }
+    }
+
+    fn remove(&self, node: &Self::Node) {
+        node.remove();
+    }
+
+    fn element_children(&self, node: &Self::Node) -> Vec<Self::Node> {
+        let mut children = vec![];
+        let mut child = node.first_element_child();
+        while let Some(element) = child {
+            child = element.next_element_sibling();
+            children.push(element);
+        }
+        children
+    }
+
+    fn tag_name(&self, node: &Self::Node) -> String {
+        node.tag_name().to_lowercase()
+    }
+
+    fn text_content(&self, node: &Self::Node) -> Option<String> {
+        node.text_content()
+    }
+
+    // an unset property reads as an empty string
+    fn get_style(&self, node: &Self::Node, key: &str) -> Option<String> {
+        node.dyn_ref::<HtmlElement>()
+            .and_then(|element| element.style().get_property_value(key).ok())
+            .filter(|value| !value.is_empty())
+    }
+
+    fn get_attribute(&self, node: &Self::Node, key: &str) -> Option<String> {
+        node.get_attribute(key)
+    }
+}
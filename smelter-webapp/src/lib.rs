@@ -1,10 +1,15 @@
-use std::sync::Arc;
+use std::{cell::RefCell, sync::Arc};
 
 use wasm_bindgen::prelude::*;
 
 use smelter_ui::*;
 use smelter_reflux::*;
 
+thread_local! {
+    // the instance owns the bindings and unmounts once dropped, so the app keeps it for the lifetime of the page
+    static INSTANCE: RefCell<Option<Arc<DOMDocumentInstance<WebRenderer>>>> = const { RefCell::new(None) };
+}
+
 #[wasm_bindgen(start)]
 pub fn run() -> Result<(), JsValue> {
     let mut builder = DocumentBuilder::new();
@@ -16,17 +21,21 @@ pub fn run() -> Result<(), JsValue> {
             .style("font-size", "32pt")
             .subscribe_text(&text_content);
         Division::new(context).children(|context| {
+            let mut cancellables = vec![];
             let onclick = Subscriber::new();
             onclick
-                .map(|_| Some(format!("Clicked")))
-                .bind(&text_content);
+                .map(|_| Some("Clicked".to_string()))
+                .bind(&text_content)
+                .store_in(&mut cancellables);
             Button::new(context)
                 .text("Hello world!")
                 .style("font-size", "12pt")
-                .publish_onclick(&onclick);
+                .publish_onclick(&onclick)
+                .retain(cancellables);
         });
     });
     let document = builder.build();
-    let _ = document.instantiate();
+    let instance = document.instantiate();
+    INSTANCE.with(|v| *v.borrow_mut() = Some(instance));
     Ok(())
 }